@@ -0,0 +1,69 @@
+//! Labels the reports of the `moka-local` feature with the version and git commit
+//! of the Moka checkout linked at `moka-local/checkout`.
+
+use std::{path::Path, process::Command};
+
+/// The Moka checkout, relative to `moka-local/Cargo.toml`, which is the only
+/// manifest with the `moka-local` feature.
+const MOKA_LOCAL_DIR: &str = "checkout";
+
+fn main() {
+    if std::env::var_os("CARGO_FEATURE_MOKA_LOCAL").is_none() {
+        return;
+    }
+
+    let dir = Path::new(MOKA_LOCAL_DIR);
+    println!("cargo:rerun-if-changed={MOKA_LOCAL_DIR}/Cargo.toml");
+    println!("cargo:rerun-if-changed={MOKA_LOCAL_DIR}/src");
+
+    let version = package_version(&dir.join("Cargo.toml")).unwrap_or_else(|| "unknown".into());
+    let label = match git_commit(dir) {
+        Some(commit) => format!("{version}, local {commit}"),
+        None => format!("{version}, local"),
+    };
+    println!("cargo:rustc-env=MOKA_LOCAL_LABEL={label}");
+}
+
+fn package_version(manifest: &Path) -> Option<String> {
+    let manifest: toml::Table = std::fs::read_to_string(manifest).ok()?.parse().ok()?;
+    let version = manifest.get("package")?.get("version")?.as_str()?;
+    Some(version.to_string())
+}
+
+/// Returns the abbreviated commit of the checkout, with `-dirty` appended when it
+/// has uncommitted changes.
+fn git_commit(dir: &Path) -> Option<String> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    // Do not report the commit of mokabench when the checkout is not a git
+    // repository by itself.
+    let toplevel = git(&["rev-parse", "--show-toplevel"])?;
+    if Path::new(&toplevel).canonicalize().ok()? != dir.canonicalize().ok()? {
+        return None;
+    }
+
+    // Rerun when a commit is made or another branch is checked out.
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        println!("cargo:rerun-if-changed={git_dir}/HEAD");
+        println!("cargo:rerun-if-changed={git_dir}/refs");
+        println!("cargo:rerun-if-changed={git_dir}/index");
+    }
+
+    let commit = git(&["rev-parse", "--short", "HEAD"])?;
+    let is_dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    Some(if is_dirty {
+        format!("{commit}-dirty")
+    } else {
+        commit
+    })
+}
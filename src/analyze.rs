@@ -0,0 +1,337 @@
+//! Analyzes the access pattern of a trace: the number of unique keys, the reuse
+//! (LRU stack) distances and the working set size over time. It is used to choose
+//! the capacities to benchmark the caches with.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    ops::Range,
+};
+
+use crate::{
+    cache::{DefaultHasher, Key},
+    config::Config,
+    load_gen,
+};
+
+/// The number of windows to split the trace into to see the working set size over
+/// time.
+const NUM_WINDOWS: usize = 10;
+
+/// The fractions of the maximum achievable hit ratio to recommend the capacities
+/// for.
+const TARGET_FRACTIONS: [f64; 3] = [0.25, 0.5, 0.75];
+
+pub struct TraceAnalysis {
+    pub trace: String,
+    /// The number of trace entries (lines).
+    pub num_requests: u64,
+    /// The number of block accesses. It is larger than `num_requests` when the
+    /// entries of the trace have ranges of blocks.
+    pub num_accesses: u64,
+    pub unique_keys: u64,
+    pub working_sets: Vec<WorkingSet>,
+    // The number of accesses for each reuse distance. The distance of an access is
+    // the number of distinct keys accessed since the previous access to the same
+    // key. The first accesses to the keys are not counted.
+    distance_counts: Vec<u64>,
+}
+
+/// The keys accessed in a window of the trace.
+pub struct WorkingSet {
+    /// The range of the accesses (the indices of the blocks).
+    pub accesses: Range<u64>,
+    /// The number of distinct keys accessed in the window.
+    pub unique_keys: u64,
+    /// The number of keys accessed for the first time in the trace.
+    pub new_keys: u64,
+}
+
+impl TraceAnalysis {
+    /// Reads the trace and analyzes it.
+    pub fn analyze(config: &Config) -> anyhow::Result<Self> {
+        let mut num_requests = 0;
+        let mut keys = Vec::new();
+        for entry in load_gen::trace_entries(config)? {
+            let entry = entry?;
+            num_requests += 1;
+            keys.extend(entry.range());
+        }
+
+        let mut analysis = Self::from_keys(&keys);
+        analysis.trace = config.trace_file.to_string();
+        analysis.num_requests = num_requests;
+        Ok(analysis)
+    }
+
+    fn from_keys(keys: &[Key]) -> Self {
+        let window_size = keys.len().div_ceil(NUM_WINDOWS).max(1);
+        let mut working_sets = Vec::with_capacity(NUM_WINDOWS);
+        let mut distances = StackDistances::new(keys.len());
+
+        for (i, &key) in keys.iter().enumerate() {
+            if i % window_size == 0 {
+                working_sets.push(WorkingSet {
+                    accesses: (i as u64)..((i + window_size).min(keys.len()) as u64),
+                    unique_keys: 0,
+                    new_keys: 0,
+                });
+            }
+            let window = working_sets.last_mut().unwrap();
+
+            match distances.record(i, key) {
+                Some(prev) => {
+                    if prev < window.accesses.start as usize {
+                        window.unique_keys += 1;
+                    }
+                }
+                None => {
+                    window.unique_keys += 1;
+                    window.new_keys += 1;
+                }
+            }
+        }
+
+        Self {
+            trace: String::new(),
+            num_requests: 0,
+            num_accesses: keys.len() as u64,
+            unique_keys: distances.unique_keys() as u64,
+            working_sets,
+            distance_counts: distances.counts,
+        }
+    }
+
+    /// The miss ratio for the first accesses to the keys, which any cache cannot
+    /// avoid.
+    pub fn compulsory_miss_ratio(&self) -> f64 {
+        self.unique_keys as f64 / self.num_accesses as f64
+    }
+
+    /// The hit ratio of an LRU cache with the capacity. An access hits when its
+    /// reuse distance is smaller than the capacity.
+    pub fn lru_hit_ratio(&self, capacity: usize) -> f64 {
+        let end = capacity.min(self.distance_counts.len());
+        let hits: u64 = self.distance_counts[..end].iter().sum();
+        hits as f64 / self.num_accesses as f64
+    }
+
+    /// Returns the number of accesses for the reuse distances in the ranges of
+    /// powers of two (`0..1`, `1..2`, `2..4`, `4..8`, ...).
+    pub fn reuse_distance_histogram(&self) -> Vec<(Range<usize>, u64)> {
+        let mut histogram = Vec::new();
+        let mut start = 0;
+        while start < self.distance_counts.len() {
+            let end = (start * 2).max(1);
+            let count = self.distance_counts[start..end.min(self.distance_counts.len())]
+                .iter()
+                .sum();
+            histogram.push((start..end, count));
+            start = end;
+        }
+        histogram
+    }
+
+    /// Returns the capacities at which an LRU cache reaches 25%, 50% and 75% of the
+    /// maximum achievable hit ratio (`1 - compulsory_miss_ratio`), rounded up to two
+    /// significant digits.
+    pub fn recommended_capacities(&self) -> Vec<usize> {
+        let mut capacities = capacities_at_fractions(&self.distance_counts, &TARGET_FRACTIONS, 1.0)
+            .into_iter()
+            .map(round_up_capacity)
+            .collect::<Vec<_>>();
+        capacities.dedup();
+        capacities
+    }
+
+    pub fn write_report(&self, out: &mut impl Write) -> io::Result<()> {
+        let percent = |n: u64| n as f64 * 100.0 / self.num_accesses as f64;
+
+        writeln!(out, "Trace: {}", self.trace)?;
+        writeln!(out, "Requests: {}", self.num_requests)?;
+        writeln!(out, "Accesses (blocks): {}", self.num_accesses)?;
+        writeln!(out, "Unique keys: {}", self.unique_keys)?;
+        writeln!(
+            out,
+            "Compulsory miss ratio: {:.3}%",
+            self.compulsory_miss_ratio() * 100.0
+        )?;
+        writeln!(out)?;
+
+        writeln!(out, "Reuse distance histogram:")?;
+        writeln!(
+            out,
+            "  {:>25}  {:>12}  {:>8}  {:>15}",
+            "Distance", "Accesses", "Percent", "Cumulative"
+        )?;
+        let mut cumulative = 0;
+        for (range, count) in self.reuse_distance_histogram() {
+            cumulative += count;
+            let distance = if range.len() == 1 {
+                range.start.to_string()
+            } else {
+                format!("{} - {}", range.start, range.end - 1)
+            };
+            writeln!(
+                out,
+                "  {:>25}  {:>12}  {:>7.3}%  {:>14.3}%",
+                distance,
+                count,
+                percent(count),
+                percent(cumulative)
+            )?;
+        }
+        writeln!(
+            out,
+            "  {:>25}  {:>12}  {:>7.3}%",
+            "first access",
+            self.unique_keys,
+            percent(self.unique_keys)
+        )?;
+        writeln!(out)?;
+
+        writeln!(out, "Working set size over time:")?;
+        writeln!(
+            out,
+            "  {:>25}  {:>12}  {:>12}",
+            "Accesses", "Unique Keys", "New Keys"
+        )?;
+        for ws in &self.working_sets {
+            writeln!(
+                out,
+                "  {:>25}  {:>12}  {:>12}",
+                format!("{} - {}", ws.accesses.start, ws.accesses.end - 1),
+                ws.unique_keys,
+                ws.new_keys
+            )?;
+        }
+        writeln!(out)?;
+
+        writeln!(out, "Recommended capacities:")?;
+        let capacities = self.recommended_capacities();
+        for capacity in &capacities {
+            writeln!(
+                out,
+                "  {:>12}  (LRU hit ratio {:.3}%)",
+                capacity,
+                self.lru_hit_ratio(*capacity) * 100.0
+            )?;
+        }
+        if !capacities.is_empty() {
+            let list = capacities
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            writeln!(out, "  Use them with: -c {}", list.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Rounds up the capacity to two significant digits (e.g. 12345 to 13000).
+pub(crate) fn round_up_capacity(capacity: usize) -> usize {
+    let mut unit = 1;
+    while capacity / unit >= 100 {
+        unit *= 10;
+    }
+    capacity.div_ceil(unit) * unit
+}
+
+/// Computes the reuse distances of the accesses to a sequence of keys.
+pub(crate) struct StackDistances {
+    // The position of the last access to each key.
+    last_access: HashMap<Key, usize, DefaultHasher>,
+    // Has 1 at the position of the last access to each key, so that the number of
+    // distinct keys accessed in a range of positions is the sum of the range.
+    last_marks: FenwickTree,
+    /// The number of accesses for each reuse distance.
+    pub(crate) counts: Vec<u64>,
+}
+
+impl StackDistances {
+    pub(crate) fn new(num_accesses: usize) -> Self {
+        Self {
+            last_access: HashMap::with_hasher(DefaultHasher),
+            last_marks: FenwickTree::new(num_accesses),
+            counts: Vec::new(),
+        }
+    }
+
+    /// Records the access to the key at the position, and returns the position of
+    /// the previous access to the key.
+    pub(crate) fn record(&mut self, position: usize, key: Key) -> Option<usize> {
+        let prev = self.last_access.insert(key, position);
+        if let Some(prev) = prev {
+            let distance = self.last_marks.sum(position) - self.last_marks.sum(prev + 1);
+            if self.counts.len() <= distance {
+                self.counts.resize(distance + 1, 0);
+            }
+            self.counts[distance] += 1;
+            self.last_marks.add(prev, -1);
+        }
+        self.last_marks.add(position, 1);
+        prev
+    }
+
+    pub(crate) fn unique_keys(&self) -> usize {
+        self.last_access.len()
+    }
+}
+
+/// Returns the smallest LRU capacities to hit the given fractions of the accesses
+/// that have reuse distances. The distances are divided by `scale` to get the
+/// capacities.
+pub(crate) fn capacities_at_fractions(
+    distance_counts: &[u64],
+    fractions: &[f64],
+    scale: f64,
+) -> Vec<usize> {
+    let reusable: u64 = distance_counts.iter().sum();
+    let mut capacities = Vec::new();
+    let mut hits = 0;
+    let mut targets = fractions.iter().peekable();
+    for (distance, count) in distance_counts.iter().enumerate() {
+        hits += count;
+        while let Some(fraction) = targets.peek() {
+            if (hits as f64) < reusable as f64 * *fraction {
+                break;
+            }
+            capacities.push((((distance + 1) as f64 / scale).ceil() as usize).max(1));
+            targets.next();
+        }
+    }
+    capacities
+}
+
+/// A Fenwick tree (binary indexed tree) to compute the prefix sums in
+/// `O(log n)`.
+struct FenwickTree {
+    tree: Vec<i32>,
+}
+
+impl FenwickTree {
+    fn new(len: usize) -> Self {
+        Self {
+            tree: vec![0; len + 1],
+        }
+    }
+
+    fn add(&mut self, index: usize, delta: i32) {
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] += delta;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Returns the sum of `0..end`.
+    fn sum(&self, end: usize) -> usize {
+        let mut sum = 0i64;
+        let mut i = end;
+        while i > 0 {
+            sum += self.tree[i] as i64;
+            i -= i & i.wrapping_neg();
+        }
+        sum as usize
+    }
+}
@@ -0,0 +1,357 @@
+//! A compact binary format of the parsed trace entries, written by `mokabench
+//! convert` next to the trace file. It is memory-mapped and replayed without
+//! parsing the text lines.
+//!
+//! The file starts with an 8-byte magic, the number of entries (little-endian
+//! `u64`) and the format of the trace file (one byte, see `TraceFileGroup`),
+//! followed by the entries. Each entry has three LEB128 variable-length
+//! integers: the difference of the start of the range of keys from the previous
+//! entry (zigzag-encoded), the length of the range, and the difference of the line
+//! number from the previous entry. As the ranges and line numbers of consecutive
+//! entries are usually close, most entries take a few bytes.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use memmap2::Mmap;
+
+use crate::{
+    load_gen,
+    parser::TraceEntry,
+    trace_file::{self, append_extension, TraceFile, TraceFileGroup},
+};
+
+/// The extension appended to the path of the trace file.
+pub const EXTENSION: &str = "mbt";
+
+const MAGIC: &[u8; 8] = b"MOKATRC1";
+const HEADER_LEN: usize = 17;
+
+/// Returns the path of the binary file for the trace file at `trace_path`, e.g.
+/// `S3.lis.mbt` for `S3.lis`.
+pub fn binary_path(trace_path: &Path) -> PathBuf {
+    append_extension(trace_path, EXTENSION)
+}
+
+pub fn is_binary_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == EXTENSION)
+}
+
+/// Parses the trace file, and writes the entries to its binary file. Returns the
+/// path of the binary file and the number of entries.
+pub fn convert(trace_file: &TraceFile) -> anyhow::Result<(PathBuf, u64)> {
+    if trace_file.is_synthetic() {
+        anyhow::bail!("The synthetic workload {trace_file} is not a file to convert");
+    }
+    let trace_path = trace_file.path();
+    if is_binary_path(&trace_path) {
+        anyhow::bail!("{} is already a binary trace file", trace_path.display());
+    }
+
+    let format = TraceFileGroup::new(trace_file);
+    let entries = load_gen::parse_trace_file(trace_file, None)?;
+    let path = binary_path(&trace_path);
+    // Write to a temporary file first, so that an interrupted conversion does not
+    // leave a broken binary file to be used by the benchmarks.
+    let tmp_path = append_extension(&path, "tmp");
+    let result = write_entries(&tmp_path, entries, format).and_then(|count| {
+        fs::rename(&tmp_path, &path).with_context(|| {
+            format!("Cannot rename {} to {}", tmp_path.display(), path.display())
+        })?;
+        Ok(count)
+    });
+    match result {
+        Ok(count) => Ok((path, count)),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+fn write_entries(
+    path: &Path,
+    entries: load_gen::TraceEntries,
+    format: TraceFileGroup,
+) -> anyhow::Result<u64> {
+    let f = File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+    let mut out = BufWriter::new(f);
+
+    // The number of entries is written after all entries are written.
+    out.write_all(&[0; HEADER_LEN])?;
+    let mut count = 0u64;
+    let mut prev = TraceEntry::new(0..0, 0);
+    for entry in entries {
+        let entry = entry?;
+        let start_delta = (entry.range().start as i64).wrapping_sub(prev.range().start as i64);
+        let line_delta = entry
+            .line_number()
+            .checked_sub(prev.line_number())
+            .context("The line numbers must be increasing")?;
+        write_varint(&mut out, zigzag_encode(start_delta))?;
+        write_varint(&mut out, entry.range().len() as u64)?;
+        write_varint(&mut out, line_delta as u64)?;
+        prev = entry;
+        count += 1;
+    }
+
+    let mut f = out.into_inner()?;
+    write_header(&mut f, count, format)?;
+    f.sync_all()?;
+    Ok(count)
+}
+
+fn write_header(f: &mut File, count: u64, format: TraceFileGroup) -> anyhow::Result<()> {
+    f.seek(SeekFrom::Start(0))?;
+    f.write_all(MAGIC)?;
+    f.write_all(&count.to_le_bytes())?;
+    f.write_all(&[format_to_byte(format)])?;
+    Ok(())
+}
+
+/// Returns the number of entries and the format of the trace file, or `None` if
+/// `header` is not of a binary trace file of this version.
+fn read_header(header: &[u8]) -> Option<(u64, TraceFileGroup)> {
+    if header.len() < HEADER_LEN || &header[..MAGIC.len()] != MAGIC {
+        return None;
+    }
+    let count = u64::from_le_bytes(header[MAGIC.len()..16].try_into().unwrap());
+    let format = format_from_byte(header[16])?;
+    Some((count, format))
+}
+
+fn format_to_byte(format: TraceFileGroup) -> u8 {
+    match format {
+        TraceFileGroup::Arc => 0,
+        TraceFileGroup::Lirs => 1,
+    }
+}
+
+fn format_from_byte(byte: u8) -> Option<TraceFileGroup> {
+    match byte {
+        0 => Some(TraceFileGroup::Arc),
+        1 => Some(TraceFileGroup::Lirs),
+        _ => None,
+    }
+}
+
+/// Returns the path of the binary file to replay instead of the trace file at
+/// `trace_path`, if the binary file exists, was converted from the trace file in
+/// the `format`, and is not older than the trace file.
+pub(crate) fn find_up_to_date(trace_path: &Path, format: TraceFileGroup) -> Option<PathBuf> {
+    let path = binary_path(trace_path);
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+
+    // The trace file may exist only in the compressed form, or as the parts of a
+    // multi-part archive.
+    let zst_path = append_extension(trace_path, "zst");
+    let parts = trace_file::find_parts(&zst_path).ok()?;
+    let is_stale = [trace_path, &zst_path]
+        .into_iter()
+        .chain(parts.iter().map(PathBuf::as_path))
+        .any(|p| {
+            fs::metadata(p)
+                .and_then(|m| m.modified())
+                .is_ok_and(|trace_modified| trace_modified > modified)
+        });
+    if is_stale {
+        return None;
+    }
+
+    let mut header = [0; HEADER_LEN];
+    File::open(&path).ok()?.read_exact(&mut header).ok()?;
+    let (_, binary_format) = read_header(&header)?;
+    (binary_format == format).then_some(path)
+}
+
+/// The entries of a memory-mapped binary trace file.
+pub(crate) struct BinaryTraceEntries {
+    mmap: Mmap,
+    remaining: u64,
+    offset: usize,
+    start: u64,
+    line_number: u64,
+}
+
+impl BinaryTraceEntries {
+    /// Opens the binary trace file at `path`. Returns an error if the file was
+    /// converted from a trace file in another format than `format`, if given.
+    pub(crate) fn open(path: &Path, format: Option<TraceFileGroup>) -> anyhow::Result<Self> {
+        let f = File::open(path)
+            .with_context(|| format!("Cannot open binary trace file {}", path.display()))?;
+        // SAFETY: The file is only read. It must not be modified while the
+        // benchmark is running, as with the text trace files.
+        let mmap = unsafe { Mmap::map(&f) }
+            .with_context(|| format!("Cannot map binary trace file {}", path.display()))?;
+
+        let Some((remaining, binary_format)) = read_header(&mmap) else {
+            anyhow::bail!(
+                "{} is not a binary trace file of this version. Convert the trace again",
+                path.display()
+            );
+        };
+        if let Some(format) = format.filter(|f| *f != binary_format) {
+            anyhow::bail!(
+                r#"The binary trace file {} was converted from a trace file in the "{}" format, not "{}""#,
+                path.display(),
+                binary_format.sub_dir(),
+                format.sub_dir()
+            );
+        }
+
+        Ok(Self {
+            mmap,
+            remaining,
+            offset: HEADER_LEN,
+            start: 0,
+            line_number: 0,
+        })
+    }
+
+    fn read_varint(&mut self) -> anyhow::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let Some(byte) = self.mmap.get(self.offset) else {
+                anyhow::bail!("The binary trace file is truncated. Convert the trace again");
+            };
+            self.offset += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        anyhow::bail!("The binary trace file is broken. Convert the trace again")
+    }
+
+    fn read_entry(&mut self) -> anyhow::Result<TraceEntry> {
+        self.start = self
+            .start
+            .wrapping_add_signed(zigzag_decode(self.read_varint()?));
+        let len = self.read_varint()?;
+        self.line_number += self.read_varint()?;
+        let start = self.start as usize;
+        Ok(TraceEntry::new(
+            start..(start + len as usize),
+            self.line_number as usize,
+        ))
+    }
+}
+
+impl Iterator for BinaryTraceEntries {
+    type Item = anyhow::Result<TraceEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let entry = self.read_entry();
+        if entry.is_err() {
+            // Do not read the broken file any further.
+            self.remaining = 0;
+        }
+        Some(entry)
+    }
+}
+
+fn write_varint(out: &mut impl Write, mut value: u64) -> std::io::Result<()> {
+    while value >= 0x80 {
+        out.write_all(&[(value as u8) | 0x80])?;
+        value >>= 7;
+    }
+    out.write_all(&[value as u8])
+}
+
+fn zigzag_encode(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn zigzag_decode(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, ops::Range, path::PathBuf};
+
+    use super::{write_entries, BinaryTraceEntries};
+    use crate::{parser::TraceEntry, trace_file::TraceFileGroup};
+
+    /// The (range, line number) pairs of the entries.
+    fn entries() -> Vec<(Range<usize>, usize)> {
+        vec![
+            (1_000..1_008, 1),
+            // Decreasing starts.
+            (10..11, 2),
+            (0..1, 3),
+            // A large length.
+            (1..(1 << 62), 4),
+            // Starts that are not in the range of `i64`, and their differences
+            // that overflow `i64`.
+            ((1 << 63)..((1 << 63) + 1), 5),
+            ((u64::MAX as usize - 1)..(u64::MAX as usize), 6),
+            (7..8, 7),
+            // Line gaps, e.g. by the comments and blank lines.
+            (7..8, 100),
+            (300..400, 1_000_000),
+        ]
+    }
+
+    fn write(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("mokabench-{}-{name}.mbt", std::process::id()));
+        let trace_entries = entries()
+            .into_iter()
+            .map(|(range, line_number)| Ok(TraceEntry::new(range, line_number)));
+        let count = write_entries(&path, Box::new(trace_entries), TraceFileGroup::Arc).unwrap();
+        assert_eq!(count, entries().len() as u64);
+        path
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = write("round-trip");
+        let read = BinaryTraceEntries::open(&path, Some(TraceFileGroup::Arc))
+            .unwrap()
+            .map(|entry| entry.map(|e| (e.range(), e.line_number())))
+            .collect::<anyhow::Result<Vec<_>>>();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), entries());
+    }
+
+    #[test]
+    fn other_format() {
+        let path = write("other-format");
+        let result = BinaryTraceEntries::open(&path, Some(TraceFileGroup::Lirs));
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let path = write("truncated");
+        let len = fs::metadata(&path).unwrap().len();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 2)
+            .unwrap();
+        let read = BinaryTraceEntries::open(&path, None)
+            .unwrap()
+            .collect::<Vec<_>>();
+        fs::remove_file(&path).unwrap();
+
+        // The entries before the last one are intact, and then the iteration stops
+        // with the error.
+        let (last, intact) = read.split_last().unwrap();
+        assert_eq!(intact.len(), entries().len() - 1);
+        assert!(intact.iter().all(Result::is_ok));
+        let error = last.as_ref().unwrap_err().to_string();
+        assert!(error.contains("truncated"), "{error}");
+    }
+}
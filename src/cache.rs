@@ -1,10 +1,10 @@
-use std::{
-    hash::{BuildHasher, Hash, Hasher},
-    sync::Arc,
-};
+use std::{hash::BuildHasher, sync::Arc, time::Instant};
 
 use crate::{
-    config::Config, eviction_counters::EvictionCounters, parser::TraceEntry, Command, Report,
+    config::{Config, ValueKind},
+    eviction_counters::EvictionCounters,
+    parser::{Request, TraceEntry},
+    Command, Report,
 };
 
 use async_trait::async_trait;
@@ -14,6 +14,18 @@ pub(crate) mod hashlink;
 #[cfg(any(feature = "mini-moka", feature = "moka-v08", feature = "moka-v09"))]
 pub(crate) mod mini_moka_driver;
 pub(crate) mod moka_driver;
+#[cfg(feature = "moka-local")]
+pub(crate) mod moka_driver_local;
+#[cfg(any(
+    feature = "moka-v08",
+    feature = "moka-v09",
+    feature = "moka-v010",
+    feature = "moka-v011"
+))]
+pub(crate) mod moka_driver_v1;
+#[cfg(feature = "moka-v012")]
+pub(crate) mod moka_driver_v2;
+pub(crate) mod policy;
 #[cfg(feature = "quick_cache")]
 pub(crate) mod quick_cache;
 #[cfg(feature = "stretto")]
@@ -22,31 +34,215 @@ pub(crate) mod stretto;
 pub(crate) mod tiny_ufo;
 
 pub(crate) type Key = usize;
-pub(crate) type Value = (u32, Arc<[u8]>);
+pub(crate) type Value = (u32, Payload);
+
+/// The data of a value, of the type and size given by `config.value_kind` and
+/// `config.value_size`. Only the byte of the key in it is read back, by the
+/// predicate of `invalidate_entries_if`.
+#[derive(Clone)]
+pub(crate) enum Payload {
+    ArcSlice(Arc<[u8]>),
+    Vec(Vec<u8>),
+    String(String),
+    Struct(Box<Record>),
+}
+
+// The fields except the key are only allocated, cloned and dropped.
+#[derive(Clone)]
+#[allow(dead_code)]
+pub(crate) struct Record {
+    key: usize,
+    name: String,
+    tags: Vec<String>,
+    data: Vec<u8>,
+}
+
+const NUM_RECORD_TAGS: usize = 4;
+
+impl Payload {
+    /// Creates the payload of `size` bytes (at least one) for the key.
+    fn new(kind: ValueKind, size: usize, key: usize) -> Self {
+        let byte = (key % 256) as u8;
+        match kind {
+            ValueKind::ArcSlice => Self::ArcSlice(Self::bytes(size, byte).into()),
+            ValueKind::Vec => Self::Vec(Self::bytes(size, byte)),
+            ValueKind::String => {
+                // The byte of the key is the first char, which may take two bytes
+                // in UTF-8.
+                let mut s = String::with_capacity(size + 1);
+                s.push(char::from(byte));
+                s.extend(std::iter::repeat_n('a', size.saturating_sub(s.len())));
+                Self::String(s)
+            }
+            ValueKind::Struct => {
+                // A quarter of the size goes to the name, a half to the tags, and
+                // the rest to the data.
+                let name_len = size / 4;
+                let tag_len = size / 2 / NUM_RECORD_TAGS;
+                let data_len = size - name_len - tag_len * NUM_RECORD_TAGS;
+                Self::Struct(Box::new(Record {
+                    key,
+                    name: "n".repeat(name_len),
+                    tags: (0..NUM_RECORD_TAGS).map(|_| "t".repeat(tag_len)).collect(),
+                    data: Self::bytes(data_len, byte),
+                }))
+            }
+        }
+    }
+
+    fn bytes(size: usize, first: u8) -> Vec<u8> {
+        let mut bytes = vec![0; size.max(1)];
+        bytes[0] = first;
+        bytes
+    }
+
+    /// Returns the lowest byte of the key that the payload was created for.
+    pub(crate) fn key_byte(&self) -> u8 {
+        match self {
+            Self::ArcSlice(bytes) => bytes[0],
+            Self::Vec(bytes) => bytes[0],
+            Self::String(s) => s.chars().next().map_or(0, |c| c as u8),
+            Self::Struct(record) => (record.key % 256) as u8,
+        }
+    }
+}
+
+/// The optional features of a cache driver. The benchmark skips the driver when
+/// the config needs a feature that it does not have, so the methods for the
+/// missing features are never called.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub ttl: bool,
+    pub tti: bool,
+    pub insert_once: bool,
+    pub invalidate: bool,
+    pub invalidate_all: bool,
+    pub invalidate_entries_if: bool,
+    pub iterate: bool,
+    pub size_aware: bool,
+    pub eviction_listener: bool,
+    pub entry_api: bool,
+    pub per_key_expiration: bool,
+}
+
+impl Capabilities {
+    pub const NONE: Self = Self {
+        ttl: false,
+        tti: false,
+        insert_once: false,
+        invalidate: false,
+        invalidate_all: false,
+        invalidate_entries_if: false,
+        iterate: false,
+        size_aware: false,
+        eviction_listener: false,
+        entry_api: false,
+        per_key_expiration: false,
+    };
+
+    pub const ALL: Self = Self {
+        ttl: true,
+        tti: true,
+        insert_once: true,
+        invalidate: true,
+        invalidate_all: true,
+        invalidate_entries_if: true,
+        iterate: true,
+        size_aware: true,
+        eviction_listener: true,
+        entry_api: true,
+        per_key_expiration: true,
+    };
+
+    /// Pairs of the command line option and whether it is supported.
+    fn options(&self) -> [(&'static str, bool); 11] {
+        [
+            ("ttl", self.ttl),
+            ("tti", self.tti),
+            ("insert-once", self.insert_once),
+            ("invalidate", self.invalidate),
+            ("invalidate-all", self.invalidate_all),
+            ("invalidate-entries-if", self.invalidate_entries_if),
+            ("iterate", self.iterate),
+            ("size-aware", self.size_aware),
+            ("eviction-listener", self.eviction_listener),
+            ("entry-api", self.entry_api),
+            ("per-key-expiration", self.per_key_expiration),
+        ]
+    }
+
+    /// Returns the command line options that the driver supports.
+    pub fn supported_options(&self) -> Vec<&'static str> {
+        self.options()
+            .into_iter()
+            .filter_map(|(name, supported)| supported.then_some(name))
+            .collect()
+    }
+
+    /// Returns the command line options enabled in the config that the driver does
+    /// not support. The driver can run with the config if it is empty.
+    pub fn unsupported_options(&self, config: &Config) -> Vec<&'static str> {
+        let required = Self {
+            ttl: config.ttl.is_some(),
+            tti: config.tti.is_some(),
+            insert_once: config.insert_once,
+            invalidate: config.invalidate,
+            invalidate_all: config.invalidate_all,
+            invalidate_entries_if: config.invalidate_entries_if,
+            iterate: config.iterate,
+            size_aware: config.size_aware,
+            eviction_listener: config.is_eviction_listener_enabled(),
+            entry_api: config.entry_api,
+            per_key_expiration: config.per_key_expiration,
+        };
+        required
+            .options()
+            .into_iter()
+            .zip(self.options())
+            .filter_map(|((name, required), (_, supported))| {
+                (required && !supported).then_some(name)
+            })
+            .collect()
+    }
+}
 
 pub(crate) trait CacheDriver<T> {
+    const CAPABILITIES: Capabilities;
+
     fn get_or_insert(&mut self, entry: &T, report: &mut Report);
-    fn get_or_insert_once(&mut self, entry: &T, report: &mut Report);
     fn update(&mut self, entry: &T, report: &mut Report);
 
+    // The following methods are called only when the driver has the capabilities
+    // for them.
+
+    #[allow(unused_variables)]
+    fn get_or_insert_once(&mut self, entry: &T, report: &mut Report) {
+        unreachable!("insert_once is not in the capabilities");
+    }
+
     #[allow(unused_variables)]
     fn invalidate(&mut self, entry: &T) {
-        unimplemented!();
+        unreachable!("invalidate is not in the capabilities");
     }
 
     fn invalidate_all(&mut self) {
-        unimplemented!();
+        unreachable!("invalidate_all is not in the capabilities");
     }
 
     #[allow(unused_variables)]
     fn invalidate_entries_if(&mut self, entry: &T) {
-        unimplemented!();
+        unreachable!("invalidate_entries_if is not in the capabilities");
     }
 
     fn iterate(&mut self) {
-        unimplemented!();
+        unreachable!("iterate is not in the capabilities");
     }
 
+    /// Runs the pending maintenance tasks of the cache (e.g. applying the
+    /// recorded reads and writes to the eviction policy). It is a no-op for the
+    /// caches that do not have them.
+    fn run_pending_tasks(&mut self) {}
+
     fn eviction_counters(&self) -> Option<Arc<EvictionCounters>> {
         None
     }
@@ -54,6 +250,8 @@ pub(crate) trait CacheDriver<T> {
 
 #[async_trait]
 pub(crate) trait AsyncCacheDriver<T> {
+    const CAPABILITIES: Capabilities;
+
     async fn get_or_insert(&mut self, entry: &T, report: &mut Report);
     async fn get_or_insert_once(&mut self, entry: &T, report: &mut Report);
     async fn update(&mut self, entry: &T, report: &mut Report);
@@ -61,62 +259,95 @@ pub(crate) trait AsyncCacheDriver<T> {
     fn invalidate_all(&mut self);
     fn invalidate_entries_if(&mut self, entry: &T);
     async fn iterate(&mut self);
+    async fn run_pending_tasks(&mut self);
     fn eviction_counters(&self) -> Option<Arc<EvictionCounters>>;
 }
 
 pub(crate) fn process_commands(
-    commands: Vec<Command>,
+    commands: &[Command],
     cache: &mut impl CacheDriver<TraceEntry>,
     report: &mut Report,
 ) {
     for command in commands {
-        match command {
-            Command::GetOrInsert(entry) => {
-                cache.get_or_insert(&entry, report);
-            }
-            Command::GetOrInsertOnce(entry) => {
-                cache.get_or_insert_once(&entry, report);
-            }
-            Command::Update(entry) => {
-                cache.update(&entry, report);
-            }
-            Command::Invalidate(entry) => {
-                cache.invalidate(&entry);
-            }
-            Command::InvalidateAll => cache.invalidate_all(),
-            Command::InvalidateEntriesIf(entry) => {
-                cache.invalidate_entries_if(&entry);
-            }
-            Command::Iterate => cache.iterate(),
+        process_command(command, cache, report);
+    }
+}
+
+pub(crate) fn process_command(
+    command: &Command,
+    cache: &mut impl CacheDriver<TraceEntry>,
+    report: &mut Report,
+) {
+    let op = command.operation();
+    report.count_operation(op);
+    let start = report.latencies.is_some().then(Instant::now);
+
+    match command {
+        Command::GetOrInsert(entry) => {
+            cache.get_or_insert(entry, report);
+        }
+        Command::GetOrInsertOnce(entry) => {
+            cache.get_or_insert_once(entry, report);
+        }
+        Command::Update(entry) => {
+            cache.update(entry, report);
+        }
+        Command::Invalidate(entry) => {
+            cache.invalidate(entry);
+        }
+        Command::InvalidateAll => cache.invalidate_all(),
+        Command::InvalidateEntriesIf(entry) => {
+            cache.invalidate_entries_if(entry);
         }
+        Command::Iterate => cache.iterate(),
+    }
+
+    if let Some(start) = start {
+        report.record_latency(op, start.elapsed());
     }
 }
 
 pub(crate) async fn process_commands_async(
-    commands: Vec<Command>,
+    commands: &[Command],
     cache: &mut impl AsyncCacheDriver<TraceEntry>,
     report: &mut Report,
 ) {
     for command in commands {
-        match command {
-            Command::GetOrInsert(entry) => {
-                cache.get_or_insert(&entry, report).await;
-            }
-            Command::GetOrInsertOnce(entry) => {
-                cache.get_or_insert_once(&entry, report).await;
-            }
-            Command::Update(entry) => {
-                cache.update(&entry, report).await;
-            }
-            Command::Invalidate(entry) => {
-                cache.invalidate(&entry).await;
-            }
-            Command::InvalidateAll => cache.invalidate_all(),
-            Command::InvalidateEntriesIf(entry) => {
-                cache.invalidate_entries_if(&entry);
-            }
-            Command::Iterate => cache.iterate().await,
+        process_command_async(command, cache, report).await;
+    }
+}
+
+pub(crate) async fn process_command_async(
+    command: &Command,
+    cache: &mut impl AsyncCacheDriver<TraceEntry>,
+    report: &mut Report,
+) {
+    let op = command.operation();
+    report.count_operation(op);
+    let start = report.latencies.is_some().then(Instant::now);
+
+    match command {
+        Command::GetOrInsert(entry) => {
+            cache.get_or_insert(entry, report).await;
         }
+        Command::GetOrInsertOnce(entry) => {
+            cache.get_or_insert_once(entry, report).await;
+        }
+        Command::Update(entry) => {
+            cache.update(entry, report).await;
+        }
+        Command::Invalidate(entry) => {
+            cache.invalidate(entry).await;
+        }
+        Command::InvalidateAll => cache.invalidate_all(),
+        Command::InvalidateEntriesIf(entry) => {
+            cache.invalidate_entries_if(entry);
+        }
+        Command::Iterate => cache.iterate().await,
+    }
+
+    if let Some(start) = start {
+        report.record_latency(op, start.elapsed());
     }
 }
 
@@ -148,24 +379,23 @@ impl Counters {
     }
 }
 
-const VALUE_LEN: usize = 128;
-
-pub(crate) fn make_value(config: &Config, key: usize, req_id: usize) -> Value {
-    let policy_weight = if config.size_aware {
-        let mut hasher = DefaultHasher.build_hasher();
-        req_id.hash(&mut hasher);
-        // len will be [4 .. 2^16)
-        (hasher.finish() as u16).max(4) as u32
-    } else {
-        0
-    };
-    (policy_weight, do_make_value(key))
+pub(crate) fn make_value(config: &Config, key: usize, req: Request) -> Value {
+    (
+        policy_weight(config, req),
+        Payload::new(config.value_kind, config.value_size, key),
+    )
 }
 
-fn do_make_value(key: usize) -> Arc<[u8]> {
-    let mut value = vec![0; VALUE_LEN].into_boxed_slice();
-    value[0] = (key % 256) as u8;
-    value.into()
+pub(crate) fn policy_weight(config: &Config, req: Request) -> u32 {
+    match req.weight {
+        _ if !config.size_aware => 0,
+        Some(weight) => weight,
+        None => match &config.weight_dist {
+            Some(dist) => dist.weight(config.weight_seed, req.id),
+            // len will be [4 .. 2^16)
+            None => (DefaultHasher.hash_one(req.id) as u16).max(4) as u32,
+        },
+    }
 }
 
 pub(crate) fn sleep_thread_for_insertion(config: &Config) {
@@ -1,20 +1,20 @@
 use std::sync::Arc;
 
 use crate::{
-    cache::{self, CacheDriver, Counters, DefaultHasher, Key, Value},
+    cache::{self, CacheDriver, Capabilities, Counters, DefaultHasher, Key, Value},
     config::Config,
-    parser::TraceEntry,
+    parser::{Request, TraceEntry},
     report::Report,
 };
 
 #[cfg(feature = "mini-moka")]
-use mini_moka::sync::Cache;
+use mini_moka::sync::{Cache, ConcurrentCacheExt};
 
 #[cfg(all(
     not(feature = "mini-moka"),
     any(feature = "moka-v09", feature = "moka-v08")
 ))]
-use crate::moka::dash::Cache;
+use crate::cache::moka_driver_v1::moka::dash::{Cache, ConcurrentCacheExt};
 
 #[derive(Clone)]
 pub struct MiniMokSyncCache {
@@ -47,44 +47,46 @@ impl MiniMokSyncCache {
         self.cache.get(key).is_some()
     }
 
-    fn insert(&self, key: usize, req_id: usize) {
-        let value = cache::make_value(&self.config, key, req_id);
+    fn insert(&self, key: usize, req: Request) {
+        let value = cache::make_value(&self.config, key, req);
         cache::sleep_thread_for_insertion(&self.config);
         self.cache.insert(key, value);
     }
 }
 
 impl CacheDriver<TraceEntry> for MiniMokSyncCache {
+    const CAPABILITIES: Capabilities = Capabilities {
+        ttl: true,
+        tti: true,
+        invalidate: true,
+        invalidate_all: true,
+        iterate: true,
+        size_aware: true,
+        ..Capabilities::NONE
+    };
+
     fn get_or_insert(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             if self.get(&block) {
                 counters.read_hit();
             } else {
-                self.insert(block, req_id);
+                self.insert(block, req);
                 counters.inserted();
                 counters.read_missed();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
     }
 
-    fn get_or_insert_once(&mut self, _entry: &TraceEntry, _report: &mut Report) {
-        unimplemented!();
-    }
-
     fn update(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
-            self.insert(block, req_id);
+        for (block, req) in entry.requests() {
+            self.insert(block, req);
             counters.inserted();
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -111,4 +113,8 @@ impl CacheDriver<TraceEntry> for MiniMokSyncCache {
             }
         }
     }
+
+    fn run_pending_tasks(&mut self) {
+        self.cache.sync();
+    }
 }
@@ -1,15 +1,18 @@
 use crate::{
-    cache::{self, CacheDriver, Counters, DefaultHasher, Key, Value},
+    cache::{self, CacheDriver, Capabilities, Counters, DefaultHasher, Key, Value},
     config::Config,
-    parser::TraceEntry,
+    parser::{Request, TraceEntry},
     report::Report,
 };
 
 #[cfg(feature = "mini-moka")]
 use mini_moka::unsync::{Cache, CacheBuilder};
 
-#[cfg(any(feature = "moka-v08", feature = "moka-v09"))]
-use crate::moka::unsync::{Cache, CacheBuilder};
+#[cfg(all(
+    not(feature = "mini-moka"),
+    any(feature = "moka-v09", feature = "moka-v08")
+))]
+use crate::cache::moka_driver_v1::moka::unsync::{Cache, CacheBuilder};
 
 pub struct MiniMokaUnsyncCache {
     config: Config,
@@ -39,8 +42,8 @@ impl MiniMokaUnsyncCache {
         self.cache.get(key).is_some()
     }
 
-    fn insert(&mut self, key: usize, req_id: usize) {
-        let value = cache::make_value(&self.config, key, req_id);
+    fn insert(&mut self, key: usize, req: Request) {
+        let value = cache::make_value(&self.config, key, req);
         cache::sleep_thread_for_insertion(&self.config);
         self.cache.insert(key, value);
     }
@@ -48,25 +51,34 @@ impl MiniMokaUnsyncCache {
     fn invalidate_entries_if(&mut self, entry: &TraceEntry) {
         for block in entry.range() {
             self.cache
-                .invalidate_entries_if(move |_k, (_s, v)| v[0] == (block % 256) as u8)
+                .invalidate_entries_if(move |_k, (_s, v)| v.key_byte() == (block % 256) as u8)
         }
     }
 }
 
 impl CacheDriver<TraceEntry> for MiniMokaUnsyncCache {
+    const CAPABILITIES: Capabilities = Capabilities {
+        ttl: true,
+        tti: true,
+        invalidate: true,
+        invalidate_all: true,
+        invalidate_entries_if: true,
+        iterate: true,
+        size_aware: true,
+        ..Capabilities::NONE
+    };
+
     fn get_or_insert(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             if self.get(&block) {
                 counters.read_hit();
             } else {
-                self.insert(block, req_id);
+                self.insert(block, req);
                 counters.inserted();
                 counters.read_missed();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -78,12 +90,10 @@ impl CacheDriver<TraceEntry> for MiniMokaUnsyncCache {
 
     fn update(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
-            self.insert(block, req_id);
+        for (block, req) in entry.requests() {
+            self.insert(block, req);
             counters.inserted();
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -0,0 +1,20 @@
+//! Drivers for the Moka checkout linked at `moka-local/checkout`. They are built
+//! from the same sources as the drivers for Moka v0.12.x.
+
+pub(crate) use mokalocal as moka;
+
+use super::moka_driver::{
+    AsyncGetOrInsertOnce, GetOrInsertOnce, InitClosureError1, InitClosureError2, InitClosureType,
+};
+
+// The modules are loaded again by `moka_driver_v2` when `moka-v012` is enabled,
+// but they use a different `moka` crate here.
+#[allow(clippy::duplicate_mod)]
+#[path = "moka_driver_v2/async_cache.rs"]
+pub(crate) mod async_cache;
+#[allow(clippy::duplicate_mod)]
+#[path = "moka_driver_v2/sync_cache.rs"]
+pub(crate) mod sync_cache;
+#[allow(clippy::duplicate_mod)]
+#[path = "moka_driver_v2/sync_segmented.rs"]
+pub(crate) mod sync_segmented;
@@ -0,0 +1,35 @@
+//! Drivers for Moka v0.11.x or earlier. Only one of these versions can be
+//! compiled in, as the drivers use the crate features to adapt to the API of the
+//! version.
+
+#[cfg(feature = "moka-v011")]
+pub(crate) use moka011 as moka;
+
+#[cfg(feature = "moka-v010")]
+pub(crate) use moka010 as moka;
+
+#[cfg(feature = "moka-v09")]
+pub(crate) use moka09 as moka;
+
+#[cfg(feature = "moka-v08")]
+pub(crate) use moka08 as moka;
+
+use super::moka_driver::{
+    AsyncGetOrInsertOnce, GetOrInsertOnce, InitClosureError1, InitClosureError2, InitClosureType,
+};
+use crate::cache::Capabilities;
+
+pub(crate) mod async_cache;
+pub(crate) mod sync_cache;
+pub(crate) mod sync_segmented;
+
+/// The capabilities of the drivers. They depend on the compiled-in version.
+const CAPABILITIES: Capabilities = Capabilities {
+    // Since Moka v0.9
+    eviction_listener: cfg!(not(feature = "moka-v08")),
+    // Since Moka v0.10
+    entry_api: cfg!(not(any(feature = "moka-v08", feature = "moka-v09"))),
+    // Since Moka v0.11
+    per_key_expiration: cfg!(feature = "moka-v011"),
+    ..Capabilities::ALL
+};
@@ -1,13 +1,13 @@
 //! Driver for `moka::future::Cache` v0.11.x or earlier.
 
+use super::moka::future::{Cache, ConcurrentCacheExt};
 use super::{AsyncGetOrInsertOnce, InitClosureError1, InitClosureError2, InitClosureType};
 use crate::cache::{Key, Value};
-use crate::moka::future::Cache;
 use crate::{
     async_rt_helper as rt,
-    cache::{self, AsyncCacheDriver, Counters, DefaultHasher},
+    cache::{self, AsyncCacheDriver, Capabilities, Counters, DefaultHasher},
     config::Config,
-    parser::TraceEntry,
+    parser::{Request, TraceEntry},
     report::Report,
     EvictionCounters,
 };
@@ -156,8 +156,8 @@ impl<I> MokaAsyncCache<I> {
         self.cache.get(&key).is_some()
     }
 
-    async fn insert(&self, key: usize, req_id: usize) {
-        let value = cache::make_value(&self.config, key, req_id);
+    async fn insert(&self, key: usize, req: Request) {
+        let value = cache::make_value(&self.config, key, req);
         cache::sleep_task_for_insertion(&self.config).await;
         self.cache.insert(key, value).await;
     }
@@ -168,19 +168,19 @@ impl<I> AsyncCacheDriver<TraceEntry> for MokaAsyncCache<I>
 where
     I: AsyncGetOrInsertOnce + Send + Sync,
 {
+    const CAPABILITIES: Capabilities = super::CAPABILITIES;
+
     async fn get_or_insert(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             if self.get(block) {
                 counters.read_hit();
             } else {
-                self.insert(block, req_id).await;
+                self.insert(block, req).await;
                 counters.inserted();
                 counters.read_missed();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -194,12 +194,10 @@ where
 
     async fn update(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
-            self.insert(block, req_id).await;
+        for (block, req) in entry.requests() {
+            self.insert(block, req).await;
             counters.inserted();
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -218,7 +216,7 @@ where
     fn invalidate_entries_if(&mut self, entry: &TraceEntry) {
         for block in entry.range() {
             self.cache
-                .invalidate_entries_if(move |_k, (_s, v)| v[0] == (block % 256) as u8)
+                .invalidate_entries_if(move |_k, (_s, v)| v.key_byte() == (block % 256) as u8)
                 .expect("invalidate_entries_if failed");
         }
     }
@@ -234,6 +232,10 @@ where
         }
     }
 
+    async fn run_pending_tasks(&mut self) {
+        self.cache.sync();
+    }
+
     fn eviction_counters(&self) -> Option<Arc<EvictionCounters>> {
         self.eviction_counters.as_ref().map(Arc::clone)
     }
@@ -252,17 +254,14 @@ pub(crate) struct GetWith {
 impl AsyncGetOrInsertOnce for GetWith {
     async fn get_or_insert_once(&self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
         let is_inserted = Arc::new(AtomicBool::default());
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             {
                 let is_inserted2 = Arc::clone(&is_inserted);
                 match InitClosureType::select(block) {
-                    InitClosureType::GetOrInsert => {
-                        self.get_with(block, req_id, is_inserted2).await
-                    }
-                    ty => self.try_get_with(ty, block, req_id, is_inserted2).await,
+                    InitClosureType::GetOrInsert => self.get_with(block, req, is_inserted2).await,
+                    ty => self.try_get_with(ty, block, req, is_inserted2).await,
                 }
             }
 
@@ -273,7 +272,6 @@ impl AsyncGetOrInsertOnce for GetWith {
             } else {
                 counters.read_hit();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -281,12 +279,12 @@ impl AsyncGetOrInsertOnce for GetWith {
 }
 
 impl GetWith {
-    async fn get_with(&self, key: usize, req_id: usize, is_inserted: Arc<AtomicBool>) {
+    async fn get_with(&self, key: usize, req: Request, is_inserted: Arc<AtomicBool>) {
         self.cache
             .get_with(key, async {
                 cache::sleep_task_for_insertion(&self.config).await;
                 is_inserted.store(true, Ordering::Release);
-                cache::make_value(&self.config, key, req_id)
+                cache::make_value(&self.config, key, req)
             })
             .await;
     }
@@ -295,7 +293,7 @@ impl GetWith {
         &self,
         ty: InitClosureType,
         key: usize,
-        req_id: usize,
+        req: Request,
         is_inserted: Arc<AtomicBool>,
     ) {
         match ty {
@@ -304,7 +302,7 @@ impl GetWith {
                 .try_get_with(key, async {
                     cache::sleep_task_for_insertion(&self.config).await;
                     is_inserted.store(true, Ordering::Release);
-                    Ok(cache::make_value(&self.config, key, req_id)) as Result<_, InitClosureError1>
+                    Ok(cache::make_value(&self.config, key, req)) as Result<_, InitClosureError1>
                 })
                 .await
                 .is_ok(),
@@ -313,7 +311,7 @@ impl GetWith {
                 .try_get_with(key, async {
                     cache::sleep_task_for_insertion(&self.config).await;
                     is_inserted.store(true, Ordering::Release);
-                    Ok(cache::make_value(&self.config, key, req_id)) as Result<_, InitClosureError2>
+                    Ok(cache::make_value(&self.config, key, req)) as Result<_, InitClosureError2>
                 })
                 .await
                 .is_ok(),
@@ -345,12 +343,11 @@ mod entry_api {
     impl AsyncGetOrInsertOnce for EntryOrInsertWith {
         async fn get_or_insert_once(&self, entry: &TraceEntry, report: &mut Report) {
             let mut counters = Counters::default();
-            let mut req_id = entry.line_number();
 
-            for block in entry.range() {
+            for (block, req) in entry.requests() {
                 let is_inserted = match InitClosureType::select(block) {
-                    InitClosureType::GetOrInsert => self.entry_or_insert_with(block, req_id).await,
-                    ty => self.entry_or_try_insert_with(ty, block, req_id).await,
+                    InitClosureType::GetOrInsert => self.entry_or_insert_with(block, req).await,
+                    ty => self.entry_or_try_insert_with(ty, block, req).await,
                 };
 
                 if is_inserted {
@@ -359,7 +356,6 @@ mod entry_api {
                 } else {
                     counters.read_hit();
                 }
-                req_id += 1;
             }
 
             counters.add_to_report(report);
@@ -367,12 +363,12 @@ mod entry_api {
     }
 
     impl EntryOrInsertWith {
-        async fn entry_or_insert_with(&self, key: usize, req_id: usize) -> bool {
+        async fn entry_or_insert_with(&self, key: usize, req: Request) -> bool {
             self.cache
                 .entry(key)
                 .or_insert_with(async {
                     cache::sleep_task_for_insertion(&self.config).await;
-                    cache::make_value(&self.config, key, req_id)
+                    cache::make_value(&self.config, key, req)
                 })
                 .await
                 .is_fresh()
@@ -382,7 +378,7 @@ mod entry_api {
             &self,
             ty: InitClosureType,
             key: usize,
-            req_id: usize,
+            req: Request,
         ) -> bool {
             match ty {
                 InitClosureType::GetOrTryInsertWithError1 => self
@@ -390,7 +386,7 @@ mod entry_api {
                     .entry(key)
                     .or_try_insert_with(async {
                         cache::sleep_task_for_insertion(&self.config).await;
-                        Ok(cache::make_value(&self.config, key, req_id))
+                        Ok(cache::make_value(&self.config, key, req))
                             as Result<_, InitClosureError1>
                     })
                     .await
@@ -401,7 +397,7 @@ mod entry_api {
                     .entry(key)
                     .or_try_insert_with(async {
                         cache::sleep_task_for_insertion(&self.config).await;
-                        Ok(cache::make_value(&self.config, key, req_id))
+                        Ok(cache::make_value(&self.config, key, req))
                             as Result<_, InitClosureError2>
                     })
                     .await
@@ -1,11 +1,11 @@
 //! Driver for `moka::sync::SegmentedCache` v0.11.x or earlier.
 
+use super::moka::sync::{ConcurrentCacheExt, SegmentedCache};
 use super::{GetOrInsertOnce, InitClosureError1, InitClosureError2, InitClosureType};
 use crate::{
-    cache::{self, CacheDriver, Counters, DefaultHasher, Key, Value},
+    cache::{self, CacheDriver, Capabilities, Counters, DefaultHasher, Key, Value},
     config::Config,
-    moka::sync::SegmentedCache,
-    parser::TraceEntry,
+    parser::{Request, TraceEntry},
     report::Report,
     EvictionCounters,
 };
@@ -137,8 +137,8 @@ impl<I> MokaSegmentedCache<I> {
 
         #[cfg(not(feature = "moka-v08"))]
         {
+            use super::moka::notification::{Configuration, DeliveryMode};
             use crate::config::RemovalNotificationMode;
-            use crate::moka::notification::{Configuration, DeliveryMode};
 
             if config.is_eviction_listener_enabled() {
                 let c0 = Arc::new(EvictionCounters::default());
@@ -174,27 +174,27 @@ impl<I> MokaSegmentedCache<I> {
         self.cache.get(key).is_some()
     }
 
-    fn insert(&self, key: usize, req_id: usize) {
-        let value = cache::make_value(&self.config, key, req_id);
+    fn insert(&self, key: usize, req: Request) {
+        let value = cache::make_value(&self.config, key, req);
         cache::sleep_thread_for_insertion(&self.config);
         self.cache.insert(key, value);
     }
 }
 
 impl<I: GetOrInsertOnce> CacheDriver<TraceEntry> for MokaSegmentedCache<I> {
+    const CAPABILITIES: Capabilities = super::CAPABILITIES;
+
     fn get_or_insert(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             if self.get(&block) {
                 counters.read_hit();
             } else {
-                self.insert(block, req_id);
+                self.insert(block, req);
                 counters.inserted();
                 counters.read_missed();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -206,12 +206,10 @@ impl<I: GetOrInsertOnce> CacheDriver<TraceEntry> for MokaSegmentedCache<I> {
 
     fn update(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
-            self.insert(block, req_id);
+        for (block, req) in entry.requests() {
+            self.insert(block, req);
             counters.inserted();
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -230,7 +228,7 @@ impl<I: GetOrInsertOnce> CacheDriver<TraceEntry> for MokaSegmentedCache<I> {
     fn invalidate_entries_if(&mut self, entry: &TraceEntry) {
         for block in entry.range() {
             self.cache
-                .invalidate_entries_if(move |_k, (_s, v)| v[0] == (block % 256) as u8)
+                .invalidate_entries_if(move |_k, (_s, v)| v.key_byte() == (block % 256) as u8)
                 .expect("invalidate_entries_if failed");
         }
     }
@@ -246,6 +244,10 @@ impl<I: GetOrInsertOnce> CacheDriver<TraceEntry> for MokaSegmentedCache<I> {
         }
     }
 
+    fn run_pending_tasks(&mut self) {
+        self.cache.sync();
+    }
+
     fn eviction_counters(&self) -> Option<Arc<EvictionCounters>> {
         self.eviction_counters.as_ref().map(Arc::clone)
     }
@@ -263,15 +265,14 @@ pub(crate) struct GetWith {
 impl GetOrInsertOnce for GetWith {
     fn get_or_insert_once(&self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
         let is_inserted = Arc::new(AtomicBool::default());
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             {
                 let is_inserted2 = Arc::clone(&is_inserted);
                 match InitClosureType::select(block) {
-                    InitClosureType::GetOrInsert => self.get_with(block, req_id, is_inserted2),
-                    ty => self.try_get_with(ty, block, req_id, is_inserted2),
+                    InitClosureType::GetOrInsert => self.get_with(block, req, is_inserted2),
+                    ty => self.try_get_with(ty, block, req, is_inserted2),
                 }
             }
 
@@ -282,7 +283,6 @@ impl GetOrInsertOnce for GetWith {
             } else {
                 counters.read_hit();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -290,11 +290,11 @@ impl GetOrInsertOnce for GetWith {
 }
 
 impl GetWith {
-    fn get_with(&self, key: usize, req_id: usize, is_inserted: Arc<AtomicBool>) {
+    fn get_with(&self, key: usize, req: Request, is_inserted: Arc<AtomicBool>) {
         self.cache.get_with(key, || {
             cache::sleep_thread_for_insertion(&self.config);
             is_inserted.store(true, Ordering::Release);
-            cache::make_value(&self.config, key, req_id)
+            cache::make_value(&self.config, key, req)
         });
     }
 
@@ -302,7 +302,7 @@ impl GetWith {
         &self,
         ty: InitClosureType,
         key: usize,
-        req_id: usize,
+        req: Request,
         is_inserted: Arc<AtomicBool>,
     ) {
         match ty {
@@ -311,7 +311,7 @@ impl GetWith {
                 .try_get_with(key, || {
                     cache::sleep_thread_for_insertion(&self.config);
                     is_inserted.store(true, Ordering::Release);
-                    Ok(cache::make_value(&self.config, key, req_id)) as Result<_, InitClosureError1>
+                    Ok(cache::make_value(&self.config, key, req)) as Result<_, InitClosureError1>
                 })
                 .is_ok(),
             InitClosureType::GetOrTyyInsertWithError2 => self
@@ -319,7 +319,7 @@ impl GetWith {
                 .try_get_with(key, || {
                     cache::sleep_thread_for_insertion(&self.config);
                     is_inserted.store(true, Ordering::Release);
-                    Ok(cache::make_value(&self.config, key, req_id)) as Result<_, InitClosureError2>
+                    Ok(cache::make_value(&self.config, key, req)) as Result<_, InitClosureError2>
                 })
                 .is_ok(),
             _ => unreachable!(),
@@ -352,12 +352,11 @@ mod entry_api {
     impl GetOrInsertOnce for EntryOrInsertWith {
         fn get_or_insert_once(&self, entry: &TraceEntry, report: &mut Report) {
             let mut counters = Counters::default();
-            let mut req_id = entry.line_number();
 
-            for block in entry.range() {
+            for (block, req) in entry.requests() {
                 let is_inserted = match InitClosureType::select(block) {
-                    InitClosureType::GetOrInsert => self.entry_or_insert_with(block, req_id),
-                    ty => self.entry_or_try_insert_with(ty, block, req_id),
+                    InitClosureType::GetOrInsert => self.entry_or_insert_with(block, req),
+                    ty => self.entry_or_try_insert_with(ty, block, req),
                 };
 
                 if is_inserted {
@@ -366,7 +365,6 @@ mod entry_api {
                 } else {
                     counters.read_hit();
                 }
-                req_id += 1;
             }
 
             counters.add_to_report(report);
@@ -374,24 +372,24 @@ mod entry_api {
     }
 
     impl EntryOrInsertWith {
-        fn entry_or_insert_with(&self, key: usize, req_id: usize) -> bool {
+        fn entry_or_insert_with(&self, key: usize, req: Request) -> bool {
             self.cache
                 .entry(key)
                 .or_insert_with(|| {
                     cache::sleep_thread_for_insertion(&self.config);
-                    cache::make_value(&self.config, key, req_id)
+                    cache::make_value(&self.config, key, req)
                 })
                 .is_fresh()
         }
 
-        fn entry_or_try_insert_with(&self, ty: InitClosureType, key: usize, req_id: usize) -> bool {
+        fn entry_or_try_insert_with(&self, ty: InitClosureType, key: usize, req: Request) -> bool {
             match ty {
                 InitClosureType::GetOrTryInsertWithError1 => self
                     .cache
                     .entry(key)
                     .or_try_insert_with(|| {
                         cache::sleep_thread_for_insertion(&self.config);
-                        Ok(cache::make_value(&self.config, key, req_id))
+                        Ok(cache::make_value(&self.config, key, req))
                             as Result<_, InitClosureError1>
                     })
                     .unwrap()
@@ -401,7 +399,7 @@ mod entry_api {
                     .entry(key)
                     .or_try_insert_with(|| {
                         cache::sleep_thread_for_insertion(&self.config);
-                        Ok(cache::make_value(&self.config, key, req_id))
+                        Ok(cache::make_value(&self.config, key, req))
                             as Result<_, InitClosureError2>
                     })
                     .unwrap()
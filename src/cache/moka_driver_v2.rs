@@ -0,0 +1,11 @@
+//! Drivers for Moka v0.12.x.
+
+pub(crate) use moka012 as moka;
+
+use super::moka_driver::{
+    AsyncGetOrInsertOnce, GetOrInsertOnce, InitClosureError1, InitClosureError2, InitClosureType,
+};
+
+pub(crate) mod async_cache;
+pub(crate) mod sync_cache;
+pub(crate) mod sync_segmented;
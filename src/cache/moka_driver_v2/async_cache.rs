@@ -1,13 +1,13 @@
 //! Driver for `moka::future::Cache` v0.12.0 or later.
 
+use super::moka::future::Cache;
 use super::{AsyncGetOrInsertOnce, InitClosureError1, InitClosureError2, InitClosureType};
 use crate::cache::{Key, Value};
-use crate::moka::future::Cache;
 use crate::{
     async_rt_helper as rt,
-    cache::{self, AsyncCacheDriver, Counters, DefaultHasher},
+    cache::{self, AsyncCacheDriver, Capabilities, Counters, DefaultHasher},
     config::Config,
-    parser::TraceEntry,
+    parser::{Request, TraceEntry},
     report::Report,
     EvictionCounters,
 };
@@ -131,8 +131,8 @@ impl<I> MokaAsyncCache<I> {
         self.cache.get(&key).await.is_some()
     }
 
-    async fn insert(&self, key: usize, req_id: usize) {
-        let value = cache::make_value(&self.config, key, req_id);
+    async fn insert(&self, key: usize, req: Request) {
+        let value = cache::make_value(&self.config, key, req);
         cache::sleep_task_for_insertion(&self.config).await;
         self.cache.insert(key, value).await;
     }
@@ -143,19 +143,19 @@ impl<I> AsyncCacheDriver<TraceEntry> for MokaAsyncCache<I>
 where
     I: AsyncGetOrInsertOnce + Send + Sync,
 {
+    const CAPABILITIES: Capabilities = Capabilities::ALL;
+
     async fn get_or_insert(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             if self.get(block).await {
                 counters.read_hit();
             } else {
-                self.insert(block, req_id).await;
+                self.insert(block, req).await;
                 counters.inserted();
                 counters.read_missed();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -169,12 +169,10 @@ where
 
     async fn update(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
-            self.insert(block, req_id).await;
+        for (block, req) in entry.requests() {
+            self.insert(block, req).await;
             counters.inserted();
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -193,7 +191,7 @@ where
     fn invalidate_entries_if(&mut self, entry: &TraceEntry) {
         for block in entry.range() {
             self.cache
-                .invalidate_entries_if(move |_k, (_s, v)| v[0] == (block % 256) as u8)
+                .invalidate_entries_if(move |_k, (_s, v)| v.key_byte() == (block % 256) as u8)
                 .expect("invalidate_entries_if failed");
         }
     }
@@ -209,6 +207,10 @@ where
         }
     }
 
+    async fn run_pending_tasks(&mut self) {
+        self.cache.run_pending_tasks().await;
+    }
+
     fn eviction_counters(&self) -> Option<Arc<EvictionCounters>> {
         self.eviction_counters.as_ref().map(Arc::clone)
     }
@@ -227,17 +229,14 @@ pub(crate) struct GetWith {
 impl AsyncGetOrInsertOnce for GetWith {
     async fn get_or_insert_once(&self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
         let is_inserted = Arc::new(AtomicBool::default());
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             {
                 let is_inserted2 = Arc::clone(&is_inserted);
                 match InitClosureType::select(block) {
-                    InitClosureType::GetOrInsert => {
-                        self.get_with(block, req_id, is_inserted2).await
-                    }
-                    ty => self.try_get_with(ty, block, req_id, is_inserted2).await,
+                    InitClosureType::GetOrInsert => self.get_with(block, req, is_inserted2).await,
+                    ty => self.try_get_with(ty, block, req, is_inserted2).await,
                 }
             }
 
@@ -248,7 +247,6 @@ impl AsyncGetOrInsertOnce for GetWith {
             } else {
                 counters.read_hit();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
@@ -256,12 +254,12 @@ impl AsyncGetOrInsertOnce for GetWith {
 }
 
 impl GetWith {
-    async fn get_with(&self, key: usize, req_id: usize, is_inserted: Arc<AtomicBool>) {
+    async fn get_with(&self, key: usize, req: Request, is_inserted: Arc<AtomicBool>) {
         self.cache
             .get_with(key, async {
                 cache::sleep_task_for_insertion(&self.config).await;
                 is_inserted.store(true, Ordering::Release);
-                cache::make_value(&self.config, key, req_id)
+                cache::make_value(&self.config, key, req)
             })
             .await;
     }
@@ -270,7 +268,7 @@ impl GetWith {
         &self,
         ty: InitClosureType,
         key: usize,
-        req_id: usize,
+        req: Request,
         is_inserted: Arc<AtomicBool>,
     ) {
         match ty {
@@ -279,7 +277,7 @@ impl GetWith {
                 .try_get_with(key, async {
                     cache::sleep_task_for_insertion(&self.config).await;
                     is_inserted.store(true, Ordering::Release);
-                    Ok(cache::make_value(&self.config, key, req_id)) as Result<_, InitClosureError1>
+                    Ok(cache::make_value(&self.config, key, req)) as Result<_, InitClosureError1>
                 })
                 .await
                 .is_ok(),
@@ -288,7 +286,7 @@ impl GetWith {
                 .try_get_with(key, async {
                     cache::sleep_task_for_insertion(&self.config).await;
                     is_inserted.store(true, Ordering::Release);
-                    Ok(cache::make_value(&self.config, key, req_id)) as Result<_, InitClosureError2>
+                    Ok(cache::make_value(&self.config, key, req)) as Result<_, InitClosureError2>
                 })
                 .await
                 .is_ok(),
@@ -319,12 +317,11 @@ mod entry_api {
     impl AsyncGetOrInsertOnce for EntryOrInsertWith {
         async fn get_or_insert_once(&self, entry: &TraceEntry, report: &mut Report) {
             let mut counters = Counters::default();
-            let mut req_id = entry.line_number();
 
-            for block in entry.range() {
+            for (block, req) in entry.requests() {
                 let is_inserted = match InitClosureType::select(block) {
-                    InitClosureType::GetOrInsert => self.entry_or_insert_with(block, req_id).await,
-                    ty => self.entry_or_try_insert_with(ty, block, req_id).await,
+                    InitClosureType::GetOrInsert => self.entry_or_insert_with(block, req).await,
+                    ty => self.entry_or_try_insert_with(ty, block, req).await,
                 };
 
                 if is_inserted {
@@ -333,7 +330,6 @@ mod entry_api {
                 } else {
                     counters.read_hit();
                 }
-                req_id += 1;
             }
 
             counters.add_to_report(report);
@@ -341,12 +337,12 @@ mod entry_api {
     }
 
     impl EntryOrInsertWith {
-        async fn entry_or_insert_with(&self, key: usize, req_id: usize) -> bool {
+        async fn entry_or_insert_with(&self, key: usize, req: Request) -> bool {
             self.cache
                 .entry(key)
                 .or_insert_with(async {
                     cache::sleep_task_for_insertion(&self.config).await;
-                    cache::make_value(&self.config, key, req_id)
+                    cache::make_value(&self.config, key, req)
                 })
                 .await
                 .is_fresh()
@@ -356,7 +352,7 @@ mod entry_api {
             &self,
             ty: InitClosureType,
             key: usize,
-            req_id: usize,
+            req: Request,
         ) -> bool {
             match ty {
                 InitClosureType::GetOrTryInsertWithError1 => self
@@ -364,7 +360,7 @@ mod entry_api {
                     .entry(key)
                     .or_try_insert_with(async {
                         cache::sleep_task_for_insertion(&self.config).await;
-                        Ok(cache::make_value(&self.config, key, req_id))
+                        Ok(cache::make_value(&self.config, key, req))
                             as Result<_, InitClosureError1>
                     })
                     .await
@@ -375,7 +371,7 @@ mod entry_api {
                     .entry(key)
                     .or_try_insert_with(async {
                         cache::sleep_task_for_insertion(&self.config).await;
-                        Ok(cache::make_value(&self.config, key, req_id))
+                        Ok(cache::make_value(&self.config, key, req))
                             as Result<_, InitClosureError2>
                     })
                     .await
@@ -0,0 +1,129 @@
+//! Simple single-threaded implementations of the classic replacement policies.
+//! They do not store values, and are used as references to compare the hit
+//! ratios of the real caches with.
+
+use parking_lot::Mutex;
+
+use super::{CacheDriver, Capabilities, Counters, Key};
+use crate::{
+    config::{Config, ReferencePolicy},
+    parser::TraceEntry,
+    report::Report,
+};
+
+use std::sync::Arc;
+
+mod arc;
+mod lfu;
+mod lirs;
+mod list;
+mod lru;
+mod random;
+mod w_tiny_lfu;
+
+use list::List;
+
+pub(crate) trait Policy {
+    /// Records an access to the key, and returns `true` if it is in the cache.
+    fn get(&mut self, key: Key) -> bool;
+
+    /// Inserts the key, which has just been missed by `get`, evicting other keys
+    /// if needed.
+    fn insert(&mut self, key: Key);
+
+    fn remove(&mut self, key: Key);
+
+    fn clear(&mut self);
+}
+
+/// Creates the policy with the capacity in number of entries.
+fn new_policy(policy: ReferencePolicy, capacity: usize) -> Box<dyn Policy + Send> {
+    match policy {
+        ReferencePolicy::Lru => Box::new(lru::Lru::new(capacity)),
+        ReferencePolicy::Lfu => Box::new(lfu::Lfu::new(capacity)),
+        ReferencePolicy::Arc => Box::new(arc::ArcPolicy::new(capacity)),
+        ReferencePolicy::Lirs => Box::new(lirs::Lirs::new(capacity)),
+        ReferencePolicy::WTinyLfu => Box::new(w_tiny_lfu::WTinyLfu::new(capacity)),
+        ReferencePolicy::Random => Box::new(random::Random::new(capacity)),
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct PolicyCache {
+    config: Arc<Config>,
+    policy: Arc<Mutex<Box<dyn Policy + Send>>>,
+}
+
+impl PolicyCache {
+    pub(crate) fn new(config: &Config, capacity: usize, policy: ReferencePolicy) -> Self {
+        Self {
+            config: Arc::new(config.clone()),
+            policy: Arc::new(Mutex::new(new_policy(policy, capacity))),
+        }
+    }
+
+    fn get_or_insert_block(&self, key: Key, counters: &mut Counters) {
+        let mut policy = self.policy.lock();
+        if policy.get(key) {
+            counters.read_hit();
+        } else {
+            super::sleep_thread_for_insertion(&self.config);
+            policy.insert(key);
+            counters.inserted();
+            counters.read_missed();
+        }
+    }
+}
+
+impl CacheDriver<TraceEntry> for PolicyCache {
+    // The policies do not store values nor have expirations.
+    const CAPABILITIES: Capabilities = Capabilities {
+        insert_once: true,
+        invalidate: true,
+        invalidate_all: true,
+        ..Capabilities::NONE
+    };
+
+    fn get_or_insert(&mut self, entry: &TraceEntry, report: &mut Report) {
+        let mut counters = Counters::default();
+
+        for block in entry.range() {
+            self.get_or_insert_block(block, &mut counters);
+        }
+
+        counters.add_to_report(report);
+    }
+
+    fn get_or_insert_once(&mut self, entry: &TraceEntry, report: &mut Report) {
+        // There is only one client, so this is the same as `get_or_insert`.
+        self.get_or_insert(entry, report);
+    }
+
+    fn update(&mut self, entry: &TraceEntry, report: &mut Report) {
+        let mut counters = Counters::default();
+
+        for block in entry.range() {
+            // The policies do not store values, so updating an entry is the same
+            // as accessing it.
+            let mut policy = self.policy.lock();
+            if !policy.get(block) {
+                super::sleep_thread_for_insertion(&self.config);
+                policy.insert(block);
+            }
+            counters.inserted();
+        }
+
+        counters.add_to_report(report);
+    }
+
+    fn invalidate(&mut self, entry: &TraceEntry) {
+        let mut policy = self.policy.lock();
+        for block in entry.range() {
+            policy.remove(block);
+        }
+    }
+
+    fn invalidate_all(&mut self) {
+        self.policy.lock().clear();
+    }
+}
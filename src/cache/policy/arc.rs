@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use super::{List, Policy};
+use crate::cache::{DefaultHasher, Key};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Which {
+    T1,
+    T2,
+    B1,
+    B2,
+}
+
+/// Adaptive Replacement Cache, described in N. Megiddo and D. S. Modha, "ARC: A
+/// Self-Tuning, Low Overhead Replacement Cache", FAST '03.
+///
+/// `t1` and `t2` hold the cached keys seen once and at least twice recently.
+/// `b1` and `b2` are their ghost lists, which hold the recently evicted keys
+/// without values, and are used to adapt the target size `p` of `t1`.
+pub(crate) struct ArcPolicy {
+    capacity: usize,
+    p: usize,
+    map: HashMap<Key, (Which, usize), DefaultHasher>,
+    t1: List,
+    t2: List,
+    b1: List,
+    b2: List,
+}
+
+impl ArcPolicy {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            p: 0,
+            map: HashMap::with_hasher(DefaultHasher),
+            t1: List::new(),
+            t2: List::new(),
+            b1: List::new(),
+            b2: List::new(),
+        }
+    }
+
+    fn list_mut(&mut self, which: Which) -> &mut List {
+        match which {
+            Which::T1 => &mut self.t1,
+            Which::T2 => &mut self.t2,
+            Which::B1 => &mut self.b1,
+            Which::B2 => &mut self.b2,
+        }
+    }
+
+    fn push_front(&mut self, which: Which, key: Key) {
+        let index = self.list_mut(which).push_front(key);
+        self.map.insert(key, (which, index));
+    }
+
+    /// Moves the LRU key of `from` to the MRU end of `to`.
+    fn demote(&mut self, from: Which, to: Which) {
+        if let Some(key) = self.list_mut(from).pop_back() {
+            self.push_front(to, key);
+        }
+    }
+
+    /// Removes the LRU key of the (ghost) list.
+    fn delete_lru(&mut self, which: Which) {
+        if let Some(key) = self.list_mut(which).pop_back() {
+            self.map.remove(&key);
+        }
+    }
+
+    /// Evicts a key from `t1` or `t2` to make room for a new key.
+    fn replace(&mut self, in_b2: bool) {
+        let t1_len = self.t1.len();
+        if t1_len > 0 && ((in_b2 && t1_len == self.p) || t1_len > self.p) || self.t2.is_empty() {
+            self.demote(Which::T1, Which::B1);
+        } else {
+            self.demote(Which::T2, Which::B2);
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.t1.len() + self.t2.len() >= self.capacity
+    }
+}
+
+impl Policy for ArcPolicy {
+    fn get(&mut self, key: Key) -> bool {
+        match self.map.get(&key) {
+            Some(&(which @ (Which::T1 | Which::T2), index)) => {
+                self.list_mut(which).remove(index);
+                self.push_front(Which::T2, key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn insert(&mut self, key: Key) {
+        if self.capacity == 0 {
+            return;
+        }
+        let c = self.capacity;
+
+        match self.map.get(&key).copied() {
+            // A ghost hit in b1: t1 should have been larger.
+            Some((Which::B1, index)) => {
+                let delta = (self.b2.len() / self.b1.len()).max(1);
+                self.p = (self.p + delta).min(c);
+                self.b1.remove(index);
+                if self.is_full() {
+                    self.replace(false);
+                }
+                self.push_front(Which::T2, key);
+            }
+            // A ghost hit in b2: t2 should have been larger.
+            Some((Which::B2, index)) => {
+                let delta = (self.b1.len() / self.b2.len()).max(1);
+                self.p = self.p.saturating_sub(delta);
+                self.b2.remove(index);
+                if self.is_full() {
+                    self.replace(true);
+                }
+                self.push_front(Which::T2, key);
+            }
+            Some(_) => unreachable!("the key is already cached"),
+            None => {
+                let l1_len = self.t1.len() + self.b1.len();
+                let total = l1_len + self.t2.len() + self.b2.len();
+                if l1_len >= c {
+                    if self.t1.len() < c {
+                        self.delete_lru(Which::B1);
+                        if self.is_full() {
+                            self.replace(false);
+                        }
+                    } else {
+                        self.delete_lru(Which::T1);
+                    }
+                } else if total >= c {
+                    if total >= 2 * c {
+                        self.delete_lru(Which::B2);
+                    }
+                    if self.is_full() {
+                        self.replace(false);
+                    }
+                }
+                self.push_front(Which::T1, key);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: Key) {
+        if let Some((which, index)) = self.map.remove(&key) {
+            self.list_mut(which).remove(index);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.p = 0;
+        self.map.clear();
+        self.t1.clear();
+        self.t2.clear();
+        self.b1.clear();
+        self.b2.clear();
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::Policy;
+use crate::cache::{DefaultHasher, Key};
+
+/// Least frequently used. Ties are broken by evicting the least recently used
+/// one. The frequency of a key is forgotten when it is evicted.
+pub(crate) struct Lfu {
+    capacity: usize,
+    // key -> (frequency, last access time)
+    map: HashMap<Key, (u64, u64), DefaultHasher>,
+    // (frequency, last access time, key), ordered from the eviction candidate.
+    order: BTreeSet<(u64, u64, Key)>,
+    clock: u64,
+}
+
+impl Lfu {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            map: HashMap::with_hasher(DefaultHasher),
+            order: BTreeSet::new(),
+            clock: 0,
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+impl Policy for Lfu {
+    fn get(&mut self, key: Key) -> bool {
+        let now = self.tick();
+        if let Some((freq, time)) = self.map.get_mut(&key) {
+            self.order.remove(&(*freq, *time, key));
+            *freq += 1;
+            *time = now;
+            self.order.insert((*freq, *time, key));
+            true
+        } else {
+            false
+        }
+    }
+
+    fn insert(&mut self, key: Key) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.map.len() >= self.capacity {
+            let (_, _, victim) = self.order.pop_first().unwrap();
+            self.map.remove(&victim);
+        }
+        let now = self.tick();
+        self.map.insert(key, (1, now));
+        self.order.insert((1, now, key));
+    }
+
+    fn remove(&mut self, key: Key) {
+        if let Some((freq, time)) = self.map.remove(&key) {
+            self.order.remove(&(freq, time, key));
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use super::{List, Policy};
+use crate::cache::{DefaultHasher, Key};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Lir,
+    HirResident,
+    HirNonResident,
+}
+
+struct Entry {
+    status: Status,
+    // The indices of the nodes in the stack `s` and the queue `q`.
+    s_index: Option<usize>,
+    q_index: Option<usize>,
+}
+
+/// Low Inter-reference Recency Set, described in S. Jiang and X. Zhang, "LIRS: An
+/// Efficient Low Inter-reference Recency Set Replacement Policy to Improve Buffer
+/// Cache Performance", SIGMETRICS '02.
+///
+/// The stack `s` holds the LIR keys and the recently accessed HIR keys, whose
+/// bottom is always a LIR key. The queue `q` holds the resident HIR keys, which
+/// take 1% of the capacity.
+pub(crate) struct Lirs {
+    capacity: usize,
+    lir_capacity: usize,
+    lir_count: usize,
+    map: HashMap<Key, Entry, DefaultHasher>,
+    s: List,
+    q: List,
+}
+
+impl Lirs {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let hir_capacity = (capacity / 100).max(1);
+        Self {
+            capacity,
+            lir_capacity: capacity.saturating_sub(hir_capacity),
+            lir_count: 0,
+            map: HashMap::with_hasher(DefaultHasher),
+            s: List::new(),
+            q: List::new(),
+        }
+    }
+
+    fn resident_count(&self) -> usize {
+        self.lir_count + self.q.len()
+    }
+
+    /// Removes the HIR keys at the bottom of the stack, so that the bottom will be
+    /// a LIR key.
+    fn prune(&mut self) {
+        while let Some(index) = self.s.back_index() {
+            let key = self.s.back().unwrap();
+            let entry = self.map.get_mut(&key).unwrap();
+            if entry.status == Status::Lir {
+                break;
+            }
+            self.s.remove(index);
+            entry.s_index = None;
+            if entry.status == Status::HirNonResident {
+                self.map.remove(&key);
+            }
+        }
+    }
+
+    /// Turns the LIR key at the bottom of the stack into a resident HIR key.
+    fn demote_bottom_lir(&mut self) {
+        // The bottom can be a HIR key when there is no LIR key in the stack.
+        self.prune();
+        if let Some(key) = self.s.pop_back() {
+            let entry = self.map.get_mut(&key).unwrap();
+            entry.status = Status::HirResident;
+            entry.s_index = None;
+            entry.q_index = Some(self.q.push_front(key));
+            self.lir_count -= 1;
+            self.prune();
+        }
+    }
+
+    /// Evicts the oldest resident HIR key. It stays in the stack as a non-resident
+    /// key if it is there.
+    fn evict(&mut self) {
+        if self.q.is_empty() {
+            self.demote_bottom_lir();
+        }
+        if let Some(key) = self.q.pop_back() {
+            let entry = self.map.get_mut(&key).unwrap();
+            entry.q_index = None;
+            if entry.s_index.is_some() {
+                entry.status = Status::HirNonResident;
+            } else {
+                self.map.remove(&key);
+            }
+        }
+    }
+}
+
+impl Policy for Lirs {
+    fn get(&mut self, key: Key) -> bool {
+        let Some(entry) = self.map.get_mut(&key) else {
+            return false;
+        };
+
+        match entry.status {
+            Status::Lir => {
+                let index = entry.s_index.unwrap();
+                let was_bottom = self.s.back_index() == Some(index);
+                self.s.move_to_front(index);
+                if was_bottom {
+                    self.prune();
+                }
+            }
+            Status::HirResident => {
+                if let Some(s_index) = entry.s_index {
+                    // Its new reuse distance is smaller than the one of the bottom
+                    // LIR key, so they switch the status.
+                    self.s.move_to_front(s_index);
+                    self.q.remove(entry.q_index.take().unwrap());
+                    entry.status = Status::Lir;
+                    self.lir_count += 1;
+                    if self.lir_count > self.lir_capacity {
+                        self.demote_bottom_lir();
+                    }
+                } else {
+                    entry.s_index = Some(self.s.push_front(key));
+                    self.q.move_to_front(entry.q_index.unwrap());
+                }
+            }
+            Status::HirNonResident => return false,
+        }
+        true
+    }
+
+    fn insert(&mut self, key: Key) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.resident_count() >= self.capacity {
+            self.evict();
+        }
+
+        if let Some(entry) = self.map.get_mut(&key) {
+            // A non-resident HIR key in the stack.
+            self.s.move_to_front(entry.s_index.unwrap());
+            entry.status = Status::Lir;
+            self.lir_count += 1;
+            if self.lir_count > self.lir_capacity {
+                self.demote_bottom_lir();
+            }
+        } else if self.lir_count < self.lir_capacity {
+            let s_index = Some(self.s.push_front(key));
+            self.map.insert(
+                key,
+                Entry {
+                    status: Status::Lir,
+                    s_index,
+                    q_index: None,
+                },
+            );
+            self.lir_count += 1;
+        } else {
+            let s_index = Some(self.s.push_front(key));
+            let q_index = Some(self.q.push_front(key));
+            self.map.insert(
+                key,
+                Entry {
+                    status: Status::HirResident,
+                    s_index,
+                    q_index,
+                },
+            );
+        }
+    }
+
+    fn remove(&mut self, key: Key) {
+        let Some(entry) = self.map.get_mut(&key) else {
+            return;
+        };
+        match entry.status {
+            Status::Lir => {
+                self.s.remove(entry.s_index.unwrap());
+                self.map.remove(&key);
+                self.lir_count -= 1;
+                self.prune();
+            }
+            Status::HirResident => {
+                self.q.remove(entry.q_index.take().unwrap());
+                // Keep it in the stack as a non-resident key.
+                if entry.s_index.is_some() {
+                    entry.status = Status::HirNonResident;
+                } else {
+                    self.map.remove(&key);
+                }
+            }
+            Status::HirNonResident => (),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.lir_count = 0;
+        self.map.clear();
+        self.s.clear();
+        self.q.clear();
+    }
+}
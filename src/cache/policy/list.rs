@@ -0,0 +1,120 @@
+use crate::cache::Key;
+
+const NIL: usize = usize::MAX;
+
+struct Node {
+    key: Key,
+    prev: usize,
+    next: usize,
+}
+
+/// A doubly linked list of keys, whose nodes are stored in a `Vec` and referred
+/// by their indices. The front is the most recently used (MRU) end, and the back
+/// is the least recently used (LRU) end.
+pub(crate) struct List {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    head: usize,
+    tail: usize,
+    len: usize,
+}
+
+impl List {
+    pub(crate) fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            len: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pushes the key to the front, and returns the index of its node.
+    pub(crate) fn push_front(&mut self, key: Key) -> usize {
+        let node = Node {
+            key,
+            prev: NIL,
+            next: self.head,
+        };
+        let index = if let Some(index) = self.free.pop() {
+            self.nodes[index] = node;
+            index
+        } else {
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        };
+
+        if self.head != NIL {
+            self.nodes[self.head].prev = index;
+        } else {
+            self.tail = index;
+        }
+        self.head = index;
+        self.len += 1;
+        index
+    }
+
+    /// Removes the node at the index and returns its key.
+    pub(crate) fn remove(&mut self, index: usize) -> Key {
+        self.unlink(index);
+        self.free.push(index);
+        self.len -= 1;
+        self.nodes[index].key
+    }
+
+    pub(crate) fn move_to_front(&mut self, index: usize) {
+        if self.head == index {
+            return;
+        }
+        self.unlink(index);
+        let node = &mut self.nodes[index];
+        node.prev = NIL;
+        node.next = self.head;
+        self.nodes[self.head].prev = index;
+        self.head = index;
+    }
+
+    /// Returns the key at the back.
+    pub(crate) fn back(&self) -> Option<Key> {
+        (self.tail != NIL).then(|| self.nodes[self.tail].key)
+    }
+
+    pub(crate) fn back_index(&self) -> Option<usize> {
+        (self.tail != NIL).then_some(self.tail)
+    }
+
+    pub(crate) fn pop_back(&mut self) -> Option<Key> {
+        self.back_index().map(|index| self.remove(index))
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.head = NIL;
+        self.tail = NIL;
+        self.len = 0;
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let Node { prev, next, .. } = self.nodes[index];
+        if prev != NIL {
+            self.nodes[prev].next = next;
+        } else {
+            self.head = next;
+        }
+        if next != NIL {
+            self.nodes[next].prev = prev;
+        } else {
+            self.tail = prev;
+        }
+    }
+}
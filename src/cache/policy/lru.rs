@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use super::{List, Policy};
+use crate::cache::{DefaultHasher, Key};
+
+/// Least recently used.
+pub(crate) struct Lru {
+    capacity: usize,
+    map: HashMap<Key, usize, DefaultHasher>,
+    list: List,
+}
+
+impl Lru {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            map: HashMap::with_hasher(DefaultHasher),
+            list: List::new(),
+        }
+    }
+}
+
+impl Policy for Lru {
+    fn get(&mut self, key: Key) -> bool {
+        if let Some(&index) = self.map.get(&key) {
+            self.list.move_to_front(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn insert(&mut self, key: Key) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.list.len() >= self.capacity {
+            let victim = self.list.pop_back().unwrap();
+            self.map.remove(&victim);
+        }
+        let index = self.list.push_front(key);
+        self.map.insert(key, index);
+    }
+
+    fn remove(&mut self, key: Key) {
+        if let Some(index) = self.map.remove(&key) {
+            self.list.remove(index);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.list.clear();
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::Policy;
+use crate::cache::{DefaultHasher, Key};
+
+// A fixed seed, so that the results are reproducible.
+const SEED: u64 = 0;
+
+/// Evicts a randomly chosen key.
+pub(crate) struct Random {
+    capacity: usize,
+    // key -> index in `keys`
+    map: HashMap<Key, usize, DefaultHasher>,
+    keys: Vec<Key>,
+    rng: StdRng,
+}
+
+impl Random {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            map: HashMap::with_hasher(DefaultHasher),
+            keys: Vec::new(),
+            rng: StdRng::seed_from_u64(SEED),
+        }
+    }
+
+    fn swap_remove(&mut self, index: usize) {
+        let key = self.keys.swap_remove(index);
+        self.map.remove(&key);
+        if let Some(&moved) = self.keys.get(index) {
+            self.map.insert(moved, index);
+        }
+    }
+}
+
+impl Policy for Random {
+    fn get(&mut self, key: Key) -> bool {
+        self.map.contains_key(&key)
+    }
+
+    fn insert(&mut self, key: Key) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.keys.len() >= self.capacity {
+            let index = self.rng.gen_range(0..self.keys.len());
+            self.swap_remove(index);
+        }
+        self.map.insert(key, self.keys.len());
+        self.keys.push(key);
+    }
+
+    fn remove(&mut self, key: Key) {
+        if let Some(&index) = self.map.get(&key) {
+            self.swap_remove(index);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.keys.clear();
+    }
+}
@@ -0,0 +1,196 @@
+use std::{collections::HashMap, hash::BuildHasher};
+
+use super::{List, Policy};
+use crate::cache::{DefaultHasher, Key};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Window,
+    Probation,
+    Protected,
+}
+
+/// Window TinyLFU, described in G. Einziger, R. Friedman and B. Manes, "TinyLFU: A
+/// Highly Efficient Cache Admission Policy", ACM Transactions on Storage, 2017.
+///
+/// New keys enter the LRU window, which takes 1% of the capacity. A key evicted
+/// from the window is admitted to the main SLRU (20% probation and 80% protected)
+/// only if it has been accessed more frequently than the victim of the main
+/// SLRU. The frequencies are estimated by a count-min sketch.
+pub(crate) struct WTinyLfu {
+    window_capacity: usize,
+    main_capacity: usize,
+    protected_capacity: usize,
+    map: HashMap<Key, (Region, usize), DefaultHasher>,
+    window: List,
+    probation: List,
+    protected: List,
+    sketch: FrequencySketch,
+}
+
+impl WTinyLfu {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let window_capacity = (capacity / 100).max(1).min(capacity);
+        let main_capacity = capacity - window_capacity;
+        Self {
+            window_capacity,
+            main_capacity,
+            protected_capacity: main_capacity * 8 / 10,
+            map: HashMap::with_hasher(DefaultHasher),
+            window: List::new(),
+            probation: List::new(),
+            protected: List::new(),
+            sketch: FrequencySketch::new(capacity),
+        }
+    }
+
+    fn list_mut(&mut self, region: Region) -> &mut List {
+        match region {
+            Region::Window => &mut self.window,
+            Region::Probation => &mut self.probation,
+            Region::Protected => &mut self.protected,
+        }
+    }
+
+    fn push_front(&mut self, region: Region, key: Key) {
+        let index = self.list_mut(region).push_front(key);
+        self.map.insert(key, (region, index));
+    }
+
+    /// Moves the keys exceeding the capacity of the protected segment to the
+    /// probation segment.
+    fn demote_protected(&mut self) {
+        while self.protected.len() > self.protected_capacity {
+            let key = self.protected.pop_back().unwrap();
+            self.push_front(Region::Probation, key);
+        }
+    }
+
+    /// Moves the key evicted from the window to the main SLRU, or discards it if
+    /// it is less popular than the victim of the main SLRU.
+    fn admit(&mut self, candidate: Key) {
+        if self.probation.len() + self.protected.len() < self.main_capacity {
+            self.push_front(Region::Probation, candidate);
+            return;
+        }
+
+        let victim = self.probation.back().or_else(|| self.protected.back());
+        let Some(victim) = victim else {
+            // The main SLRU has no space.
+            self.map.remove(&candidate);
+            return;
+        };
+        if self.sketch.frequency(candidate) > self.sketch.frequency(victim) {
+            self.remove(victim);
+            self.push_front(Region::Probation, candidate);
+        } else {
+            self.map.remove(&candidate);
+        }
+    }
+}
+
+impl Policy for WTinyLfu {
+    fn get(&mut self, key: Key) -> bool {
+        self.sketch.increment(key);
+
+        match self.map.get(&key).copied() {
+            Some((Region::Probation, index)) => {
+                self.probation.remove(index);
+                self.push_front(Region::Protected, key);
+                self.demote_protected();
+                true
+            }
+            Some((region, index)) => {
+                self.list_mut(region).move_to_front(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, key: Key) {
+        if self.window_capacity == 0 {
+            return;
+        }
+        self.push_front(Region::Window, key);
+        if self.window.len() > self.window_capacity {
+            let candidate = self.window.pop_back().unwrap();
+            self.admit(candidate);
+        }
+    }
+
+    fn remove(&mut self, key: Key) {
+        if let Some((region, index)) = self.map.remove(&key) {
+            self.list_mut(region).remove(index);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.window.clear();
+        self.probation.clear();
+        self.protected.clear();
+    }
+}
+
+const SKETCH_DEPTH: usize = 4;
+const MAX_COUNT: u8 = 15;
+const ROW_SEEDS: [u64; SKETCH_DEPTH] = [
+    0xc3a5_c85c_97cb_3127,
+    0xb492_b66f_be98_f273,
+    0x9ae1_6a3b_2f90_404f,
+    0xcbf2_9ce4_8422_2325,
+];
+
+/// A count-min sketch with 4-bit counters (stored in `u8`s). All counters are
+/// halved after every `10 * capacity` increments, so that old accesses are
+/// forgotten.
+struct FrequencySketch {
+    counters: Vec<u8>,
+    width_mask: usize,
+    additions: usize,
+    sample_size: usize,
+}
+
+impl FrequencySketch {
+    fn new(capacity: usize) -> Self {
+        let width = capacity.max(1).next_power_of_two();
+        Self {
+            counters: vec![0; width * SKETCH_DEPTH],
+            width_mask: width - 1,
+            additions: 0,
+            sample_size: capacity.max(1) * 10,
+        }
+    }
+
+    fn indices(&self, key: Key) -> [usize; SKETCH_DEPTH] {
+        let hash = DefaultHasher.hash_one(key);
+        std::array::from_fn(|row| {
+            // Rehash with a different seed for each row.
+            let h = (hash ^ ROW_SEEDS[row]).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32;
+            row * (self.width_mask + 1) + (h as usize & self.width_mask)
+        })
+    }
+
+    fn frequency(&self, key: Key) -> u8 {
+        self.indices(key)
+            .into_iter()
+            .map(|i| self.counters[i])
+            .min()
+            .unwrap_or_default()
+    }
+
+    fn increment(&mut self, key: Key) {
+        for i in self.indices(key) {
+            if self.counters[i] < MAX_COUNT {
+                self.counters[i] += 1;
+            }
+        }
+
+        self.additions += 1;
+        if self.additions >= self.sample_size {
+            self.counters.iter_mut().for_each(|c| *c /= 2);
+            self.additions /= 2;
+        }
+    }
+}
@@ -1,5 +1,9 @@
-use super::{CacheDriver, Counters, DefaultHasher, Key, Value};
-use crate::{config::Config, parser::TraceEntry, report::Report};
+use super::{CacheDriver, Capabilities, Counters, DefaultHasher, Key, Value};
+use crate::{
+    config::Config,
+    parser::{Request, TraceEntry},
+    report::Report,
+};
 
 use ::quick_cache::OptionsBuilder;
 
@@ -16,8 +20,8 @@ pub struct QuickCache {
 #[derive(Clone)]
 struct CustomWeighter(bool);
 
-impl ::quick_cache::Weighter<usize, (u32, Arc<[u8]>)> for CustomWeighter {
-    fn weight(&self, _key: &usize, val: &(u32, Arc<[u8]>)) -> u64 {
+impl ::quick_cache::Weighter<Key, Value> for CustomWeighter {
+    fn weight(&self, _key: &Key, val: &Value) -> u64 {
         if self.0 {
             val.0 as u64
         } else {
@@ -28,13 +32,6 @@ impl ::quick_cache::Weighter<usize, (u32, Arc<[u8]>)> for CustomWeighter {
 
 impl QuickCache {
     pub fn new(config: &Config, estimated_items_capacity: usize, capacity: u64) -> Self {
-        if let Some(_ttl) = config.ttl {
-            todo!()
-        }
-        if let Some(_tti) = config.tti {
-            todo!()
-        }
-
         let options = OptionsBuilder::new()
             .estimated_items_capacity(estimated_items_capacity)
             .weight_capacity(capacity)
@@ -57,44 +54,43 @@ impl QuickCache {
         self.cache.get(key).is_some()
     }
 
-    fn insert(&self, key: usize, req_id: usize) {
-        let value = super::make_value(&self.config, key, req_id);
+    fn insert(&self, key: usize, req: Request) {
+        let value = super::make_value(&self.config, key, req);
         super::sleep_thread_for_insertion(&self.config);
         self.cache.insert(key, value);
     }
 }
 
 impl CacheDriver<TraceEntry> for QuickCache {
+    // It supports only `get` and `insert`, with the weights of the values given
+    // to `CustomWeighter`.
+    const CAPABILITIES: Capabilities = Capabilities {
+        size_aware: true,
+        ..Capabilities::NONE
+    };
+
     fn get_or_insert(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             if self.get(&block) {
                 counters.read_hit();
             } else {
-                self.insert(block, req_id);
+                self.insert(block, req);
                 counters.inserted();
                 counters.read_missed();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
     }
 
-    fn get_or_insert_once(&mut self, _entry: &TraceEntry, _report: &mut Report) {
-        unimplemented!();
-    }
-
     fn update(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
-            self.insert(block, req_id);
+        for (block, req) in entry.requests() {
+            self.insert(block, req);
             counters.inserted();
-            req_id += 1;
         }
 
         counters.add_to_report(report);
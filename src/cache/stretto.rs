@@ -1,7 +1,11 @@
 use std::sync::Arc;
 
-use super::{CacheDriver, Counters, DefaultHasher, Key, Value};
-use crate::{config::Config, parser::TraceEntry, report::Report};
+use super::{CacheDriver, Capabilities, Counters, DefaultHasher, Key, Value};
+use crate::{
+    config::Config,
+    parser::{Request, TraceEntry},
+    report::Report,
+};
 
 #[derive(Clone)]
 pub struct StrettoCache {
@@ -21,16 +25,6 @@ pub struct StrettoCache {
 
 impl StrettoCache {
     pub fn new(config: &Config, capacity: usize) -> Self {
-        if let Some(_ttl) = config.ttl {
-            todo!()
-        }
-        if let Some(_tti) = config.tti {
-            todo!()
-        }
-        if config.size_aware {
-            todo!()
-        }
-
         Self {
             config: Arc::new(config.clone()),
             cache: ::stretto::Cache::builder(capacity * 10, capacity as i64)
@@ -44,44 +38,39 @@ impl StrettoCache {
         self.cache.get(key).is_some()
     }
 
-    fn insert(&self, key: usize, req_id: usize) {
-        let value = super::make_value(&self.config, key, req_id);
+    fn insert(&self, key: usize, req: Request) {
+        let value = super::make_value(&self.config, key, req);
         super::sleep_thread_for_insertion(&self.config);
         self.cache.insert(key, value, 1);
     }
 }
 
 impl CacheDriver<TraceEntry> for StrettoCache {
+    // It supports only `get` and `insert`.
+    const CAPABILITIES: Capabilities = Capabilities::NONE;
+
     fn get_or_insert(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
+        for (block, req) in entry.requests() {
             if self.get(&block) {
                 counters.read_hit();
             } else {
-                self.insert(block, req_id);
+                self.insert(block, req);
                 counters.inserted();
                 counters.read_missed();
             }
-            req_id += 1;
         }
 
         counters.add_to_report(report);
     }
 
-    fn get_or_insert_once(&mut self, _entry: &TraceEntry, _report: &mut Report) {
-        unimplemented!();
-    }
-
     fn update(&mut self, entry: &TraceEntry, report: &mut Report) {
         let mut counters = Counters::default();
-        let mut req_id = entry.line_number();
 
-        for block in entry.range() {
-            self.insert(block, req_id);
+        for (block, req) in entry.requests() {
+            self.insert(block, req);
             counters.inserted();
-            req_id += 1;
         }
 
         counters.add_to_report(report);
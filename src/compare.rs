@@ -0,0 +1,343 @@
+//! Compares two result files written with `--output-format json`, and detects the
+//! regressions of the throughput and hit ratio.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+
+use crate::output::RunRecord;
+
+/// The default threshold of the throughput regression in percent.
+pub const DEFAULT_THROUGHPUT_THRESHOLD: f64 = 5.0;
+
+/// The default threshold of the hit ratio regression in percentage points.
+pub const DEFAULT_HIT_RATIO_THRESHOLD: f64 = 1.0;
+
+/// The fields of the config that choose which runs are made, rather than define
+/// the workload of a run. They are left out of the `RunKey`, so that a run still
+/// matches when, for example, the other file was made with more capacities or
+/// caches.
+const RUN_MATRIX_FIELDS: &[&str] = &[
+    "label",
+    "trace_file",
+    "capacities",
+    "num_clients",
+    "caches",
+    "policies",
+    "opt",
+    "mrc",
+    "mrc_points",
+];
+
+/// The runs are matched by the trace, label, cache, capacity, number of clients
+/// and the workload-defining fields of the config. The runs of different versions
+/// of a cache are matched when each file has only one version of the run, e.g.
+/// before and after upgrading Moka.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RunKey {
+    pub trace: String,
+    pub label: Option<String>,
+    pub cache: String,
+    pub version: Option<String>,
+    pub capacity: u64,
+    pub num_clients: Option<u16>,
+    config: String,
+}
+
+impl RunKey {
+    fn new(record: &RunRecord) -> Self {
+        let mut config = record.config.clone();
+        if let Some(fields) = config.as_object_mut() {
+            for field in RUN_MATRIX_FIELDS {
+                fields.remove(*field);
+            }
+        }
+        // The keys of the JSON objects are sorted, so the same configs have the
+        // same string.
+        Self {
+            trace: record.trace.clone(),
+            label: record.label.clone(),
+            cache: record.report.name.clone(),
+            version: record.report.version.clone(),
+            capacity: record.report.capacity,
+            num_clients: record.report.num_workers,
+            config: config.to_string(),
+        }
+    }
+
+    fn without_version(&self) -> Self {
+        Self {
+            version: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the cache name with the version, if any.
+    fn cache_with_version(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{} ({version})", self.cache),
+            None => self.cache.clone(),
+        }
+    }
+}
+
+/// The metrics of a run. They are averaged when a file has multiple records of
+/// the same run.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunMetrics {
+    /// Operations per second.
+    pub throughput: Option<f64>,
+    /// The hit ratio in percent.
+    pub hit_ratio: Option<f64>,
+}
+
+impl RunMetrics {
+    fn new(record: &RunRecord) -> Self {
+        let report = &record.report;
+        // The records written before `ops_per_sec` was added do not have it, so
+        // fall back to the reads per second.
+        let reads_per_sec = || {
+            report
+                .duration_secs
+                .filter(|secs| *secs > 0.0)
+                .map(|secs| report.read_count as f64 / secs)
+        };
+        Self {
+            throughput: report.ops_per_sec.or_else(reads_per_sec),
+            hit_ratio: report.hit_ratio.map(|r| r * 100.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Thresholds {
+    /// The largest allowed decrease of the throughput in percent.
+    pub throughput: f64,
+    /// The largest allowed decrease of the hit ratio in percentage points.
+    pub hit_ratio: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            throughput: DEFAULT_THROUGHPUT_THRESHOLD,
+            hit_ratio: DEFAULT_HIT_RATIO_THRESHOLD,
+        }
+    }
+}
+
+pub struct ComparedRun {
+    pub key: RunKey,
+    /// The version of the cache in the new file, if it is different from the old
+    /// one. Otherwise, `None`.
+    pub new_version: Option<String>,
+    pub old: RunMetrics,
+    pub new: RunMetrics,
+    /// The change of the throughput in percent.
+    pub throughput_change: Option<f64>,
+    /// The change of the hit ratio in percentage points.
+    pub hit_ratio_change: Option<f64>,
+    pub is_regression: bool,
+}
+
+pub struct Comparison {
+    pub runs: Vec<ComparedRun>,
+    pub only_in_old: Vec<RunKey>,
+    pub only_in_new: Vec<RunKey>,
+    pub thresholds: Thresholds,
+}
+
+impl Comparison {
+    pub fn new(old: &[RunRecord], new: &[RunRecord], thresholds: Thresholds) -> Self {
+        let old = average_by_key(old);
+        let mut new = average_by_key(new);
+
+        let mut pairs = Vec::new();
+        let mut unmatched_old = BTreeMap::new();
+        for (key, old) in old {
+            match new.remove(&key) {
+                Some(new) => pairs.push((key, None, old, new)),
+                None => {
+                    unmatched_old.insert(key, old);
+                }
+            }
+        }
+
+        // Match the remaining runs that differ only in the version of the cache,
+        // if there is only one such run in each file.
+        let old_counts = count_by_run(unmatched_old.keys());
+        let new_counts = count_by_run(new.keys());
+        let mut only_in_old = Vec::new();
+        for (key, old) in unmatched_old {
+            let run = key.without_version();
+            let new_key = (old_counts[&run] == 1 && new_counts.get(&run) == Some(&1))
+                .then(|| new.keys().find(|k| k.without_version() == run).cloned())
+                .flatten();
+            match new_key {
+                Some(new_key) => {
+                    let new = new.remove(&new_key).unwrap();
+                    pairs.push((key, new_key.version, old, new));
+                }
+                None => only_in_old.push(key),
+            }
+        }
+        pairs.sort_by(|p1, p2| p1.0.cmp(&p2.0));
+
+        let mut runs = Vec::new();
+        for (key, new_version, old, new) in pairs {
+            let throughput_change = old
+                .throughput
+                .zip(new.throughput)
+                .map(|(old, new)| (new - old) / old * 100.0);
+            let hit_ratio_change = old.hit_ratio.zip(new.hit_ratio).map(|(old, new)| new - old);
+            let is_regression = throughput_change.is_some_and(|c| -c > thresholds.throughput)
+                || hit_ratio_change.is_some_and(|c| -c > thresholds.hit_ratio);
+            runs.push(ComparedRun {
+                key,
+                new_version,
+                old,
+                new,
+                throughput_change,
+                hit_ratio_change,
+                is_regression,
+            });
+        }
+
+        Self {
+            runs,
+            only_in_old,
+            only_in_new: new.into_keys().collect(),
+            thresholds,
+        }
+    }
+
+    pub fn regressions(&self) -> impl Iterator<Item = &ComparedRun> {
+        self.runs.iter().filter(|r| r.is_regression)
+    }
+
+    pub fn write_report(&self, out: &mut impl Write) -> io::Result<()> {
+        let opt = |v: Option<f64>, precision: usize| match v {
+            Some(v) => format!("{v:.precision$}"),
+            None => "-".to_string(),
+        };
+        let signed = |v: Option<f64>| match v {
+            Some(v) => format!("{v:+.2}"),
+            None => "-".to_string(),
+        };
+
+        writeln!(
+            out,
+            "Trace, Label, Cache, Max Capacity, Clients, Old Ops/sec, New Ops/sec, \
+            Throughput Change %, Old Hit Ratio, New Hit Ratio, Hit Ratio Change, Result"
+        )?;
+        for run in &self.runs {
+            let cache = match (&run.key.version, &run.new_version) {
+                (Some(old), Some(new)) => format!("{} ({old} -> {new})", run.key.cache),
+                _ => run.key.cache_with_version(),
+            };
+            writeln!(
+                out,
+                "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
+                run.key.trace,
+                run.key.label.as_deref().unwrap_or("-"),
+                cache,
+                run.key.capacity,
+                opt(run.key.num_clients.map(|n| n as f64), 0),
+                opt(run.old.throughput, 0),
+                opt(run.new.throughput, 0),
+                signed(run.throughput_change),
+                opt(run.old.hit_ratio, 3),
+                opt(run.new.hit_ratio, 3),
+                signed(run.hit_ratio_change),
+                if run.is_regression {
+                    "REGRESSION"
+                } else {
+                    "ok"
+                }
+            )?;
+        }
+
+        for (keys, which) in [(&self.only_in_old, "old"), (&self.only_in_new, "new")] {
+            if keys.is_empty() {
+                continue;
+            }
+            writeln!(out)?;
+            writeln!(out, "Runs only in the {which} file:")?;
+            for key in keys {
+                writeln!(
+                    out,
+                    "  {}, {}, {}, {}, {}",
+                    key.trace,
+                    key.label.as_deref().unwrap_or("-"),
+                    key.cache_with_version(),
+                    key.capacity,
+                    opt(key.num_clients.map(|n| n as f64), 0),
+                )?;
+            }
+        }
+
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{} runs compared, {} regressions (thresholds: throughput -{}%, hit ratio -{} points)",
+            self.runs.len(),
+            self.regressions().count(),
+            self.thresholds.throughput,
+            self.thresholds.hit_ratio
+        )
+    }
+}
+
+/// Reads the records from a file written with `--output-format json`.
+pub fn read_records(path: &Path) -> anyhow::Result<Vec<RunRecord>> {
+    let f = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let mut records = Vec::new();
+    for (i, line) in BufReader::new(f).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line)
+            .with_context(|| format!("Cannot parse line {} of {}", i + 1, path.display()))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Counts the runs by their keys without the versions.
+fn count_by_run<'a>(keys: impl Iterator<Item = &'a RunKey>) -> BTreeMap<RunKey, usize> {
+    let mut counts = BTreeMap::new();
+    for key in keys {
+        *counts.entry(key.without_version()).or_default() += 1;
+    }
+    counts
+}
+
+fn average_by_key(records: &[RunRecord]) -> BTreeMap<RunKey, RunMetrics> {
+    let mut groups = BTreeMap::<_, Vec<_>>::new();
+    for record in records {
+        groups
+            .entry(RunKey::new(record))
+            .or_default()
+            .push(RunMetrics::new(record));
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, metrics)| {
+            let average = |values: Vec<f64>| {
+                (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+            };
+            let metrics = RunMetrics {
+                throughput: average(metrics.iter().filter_map(|m| m.throughput).collect()),
+                hit_ratio: average(metrics.iter().filter_map(|m| m.hit_ratio).collect()),
+            };
+            (key, metrics)
+        })
+        .collect()
+}
@@ -1,22 +1,93 @@
 use std::time::Duration;
 
-use crate::trace_file::TraceFile;
+use serde::{Serialize, Serializer};
 
-#[derive(Clone, Debug)]
+use crate::{
+    mrc::MrcMethod,
+    op_mix::{OpMix, Operation},
+    trace_file::TraceFile,
+    weight::{WeightDist, DEFAULT_MEAN_WEIGHT},
+};
+
+/// The default number of commands between two `run_pending_tasks` calls in the
+/// simulate mode.
+pub const DEFAULT_PENDING_TASKS_INTERVAL: usize = 64;
+
+/// The default threshold of the coefficient of variation in percent, above which
+/// the iterations of a run are flagged as high variance.
+pub const DEFAULT_MAX_CV: f64 = 5.0;
+
+/// The default capacity of the channel in the streaming mode, in chunks of
+/// commands.
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 64;
+
+/// The default number of segments of Moka's `SegmentedCache`.
+pub const DEFAULT_NUM_SEGMENTS: usize = 8;
+
+/// The default size of the values in bytes.
+pub const DEFAULT_VALUE_SIZE: usize = 128;
+
+#[derive(Clone, Debug, Serialize)]
 pub struct Config {
+    /// The label of the scenario that this config came from.
+    pub label: Option<String>,
+    #[serde(serialize_with = "serialize_display")]
     pub trace_file: TraceFile,
+    pub capacities: Option<Vec<usize>>,
+    /// The capacities are the total weight in bytes instead of the number of
+    /// entries.
+    pub capacities_in_bytes: bool,
+    #[serde(serialize_with = "serialize_secs")]
     pub ttl: Option<Duration>,
+    #[serde(serialize_with = "serialize_secs")]
     pub tti: Option<Duration>,
     pub num_clients: Option<Vec<u16>>,
+    pub num_segments: usize,
     pub repeat: Option<u16>,
+    /// Stops each run after this time, cycling the trace as needed.
+    pub time_limit: Option<Duration>,
+    /// Stops each run after this number of commands, cycling the trace as needed.
+    pub max_ops: Option<u64>,
+    // The iterations are not a part of the workload, so they are not serialized.
+    // This lets `mokabench compare` match the runs with different iterations.
+    #[serde(skip)]
+    pub iterations: usize,
+    #[serde(skip)]
+    pub warmup_iterations: usize,
+    #[serde(skip)]
+    pub max_cv: f64,
+    #[serde(skip)]
+    pub streaming: bool,
+    #[serde(skip)]
+    pub channel_capacity: usize,
+    #[serde(serialize_with = "serialize_secs")]
     pub insertion_delay: Option<Duration>,
     pub insert_once: bool,
     pub invalidate: bool,
     pub invalidate_all: bool,
     pub invalidate_entries_if: bool,
     pub iterate: bool,
+    #[serde(serialize_with = "serialize_display_opt")]
+    pub op_mix: Option<OpMix>,
+    pub latency: bool,
+    pub simulate: bool,
+    pub pending_tasks_interval: usize,
+    pub opt: bool,
+    pub caches: Option<Vec<CacheKind>>,
+    pub policies: Vec<ReferencePolicy>,
+    #[serde(serialize_with = "serialize_display_opt")]
+    pub mrc: Option<MrcMethod>,
+    pub mrc_points: Option<usize>,
     pub eviction_listener: RemovalNotificationMode,
     pub size_aware: bool,
+    pub weighted_ranges: bool,
+    #[serde(serialize_with = "serialize_display_opt")]
+    pub weight_dist: Option<WeightDist>,
+    pub weight_seed: u64,
+    /// The 1-based column of the trace files to read the weights from.
+    pub weight_column: Option<usize>,
+    pub value_kind: ValueKind,
+    pub value_size: usize,
     pub entry_api: bool,          // Since Moka v0.10
     pub per_key_expiration: bool, // Since Moka v0.11
 }
@@ -31,24 +102,105 @@ impl Config {
         insertion_delay_micros: Option<u64>,
     ) -> Self {
         Self {
+            label: None,
             trace_file,
+            capacities: None,
+            capacities_in_bytes: false,
             ttl: ttl_secs.map(Duration::from_secs),
             tti: tti_secs.map(Duration::from_secs),
             num_clients,
+            num_segments: DEFAULT_NUM_SEGMENTS,
             repeat,
+            time_limit: None,
+            max_ops: None,
+            iterations: 1,
+            warmup_iterations: 0,
+            max_cv: DEFAULT_MAX_CV,
+            streaming: false,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
             insertion_delay: insertion_delay_micros.map(Duration::from_micros),
             insert_once: false,
             invalidate: false,
             invalidate_all: false,
             invalidate_entries_if: false,
             iterate: false,
+            op_mix: None,
+            latency: false,
+            simulate: false,
+            pending_tasks_interval: DEFAULT_PENDING_TASKS_INTERVAL,
+            opt: false,
+            caches: None,
+            policies: Vec::new(),
+            mrc: None,
+            mrc_points: None,
             eviction_listener: RemovalNotificationMode::None,
             size_aware: false,
+            weighted_ranges: false,
+            weight_dist: None,
+            weight_seed: 0,
+            weight_column: None,
+            value_kind: ValueKind::ArcSlice,
+            value_size: DEFAULT_VALUE_SIZE,
             entry_api: false,
             per_key_expiration: false,
         }
     }
 
+    pub fn set_label(&mut self, v: Option<String>) {
+        self.label = v;
+    }
+
+    pub fn set_num_segments(&mut self, v: usize) {
+        self.num_segments = v;
+    }
+
+    pub fn set_time_limit(&mut self, v: Option<Duration>) {
+        self.time_limit = v;
+    }
+
+    pub fn set_max_ops(&mut self, v: Option<u64>) {
+        self.max_ops = v;
+    }
+
+    /// Enables the streaming mode, where a producer thread reads the trace while
+    /// the clients run, and sends the commands through a channel bounded to
+    /// `channel_capacity` chunks, instead of buffering the whole trace.
+    pub fn set_streaming(&mut self, v: bool, channel_capacity: usize) {
+        self.streaming = v;
+        self.channel_capacity = channel_capacity;
+    }
+
+    /// Runs each benchmark `warmup` times without reporting, and then `iterations`
+    /// times to report the statistics. The iterations whose coefficient of
+    /// variation exceeds `max_cv` percent are flagged.
+    pub fn set_iterations(&mut self, iterations: usize, warmup: usize, max_cv: f64) {
+        self.iterations = iterations;
+        self.warmup_iterations = warmup;
+        self.max_cv = max_cv;
+    }
+
+    /// Sets the capacities in number of entries.
+    pub fn set_capacities(&mut self, v: Option<Vec<usize>>) {
+        self.capacities = v;
+        self.capacities_in_bytes = false;
+    }
+
+    /// Sets the capacities in bytes, which are used as the max capacities of the
+    /// caches as is. It also enables the size-aware mode.
+    pub fn set_capacities_in_bytes(&mut self, v: Vec<usize>) {
+        self.capacities = Some(v);
+        self.capacities_in_bytes = true;
+        self.size_aware = true;
+    }
+
+    /// Returns the capacities to run the benchmarks with. They are the ones given
+    /// by `set_capacities`, or the default capacities of the trace file.
+    pub fn capacities(&self) -> &[usize] {
+        self.capacities
+            .as_deref()
+            .unwrap_or_else(|| self.trace_file.default_capacities())
+    }
+
     pub fn set_insert_once(&mut self, v: bool) {
         self.insert_once = v;
     }
@@ -69,6 +221,61 @@ impl Config {
         self.iterate = v;
     }
 
+    /// Sets the operation mix, which replaces the fixed rules to choose the
+    /// operations. It also enables the flags for the operations in the mix (e.g.
+    /// `invalidate_entries_if`), so that the caches are configured to support them.
+    pub fn set_op_mix(&mut self, v: Option<OpMix>) {
+        if let Some(mix) = &v {
+            self.insert_once = mix.contains(Operation::GetWith);
+            self.invalidate = mix.contains(Operation::Invalidate);
+            self.invalidate_all = mix.contains(Operation::InvalidateAll);
+            self.invalidate_entries_if = mix.contains(Operation::InvalidateEntriesIf);
+            self.iterate = mix.contains(Operation::Iterate);
+        }
+        self.op_mix = v;
+    }
+
+    pub fn set_latency(&mut self, v: bool) {
+        self.latency = v;
+    }
+
+    /// Enables the simulate mode, which drives each cache from a single client in
+    /// the trace order, and runs the pending tasks of the cache every `interval`
+    /// commands so that the hit ratios are reproducible.
+    pub fn set_simulate(&mut self, v: bool, pending_tasks_interval: usize) {
+        self.simulate = v;
+        self.pending_tasks_interval = pending_tasks_interval;
+    }
+
+    /// Also reports the hit ratio of Belady's optimal policy for each capacity.
+    pub fn set_opt(&mut self, v: bool) {
+        self.opt = v;
+    }
+
+    /// Sets the caches to run. `None` runs all caches compiled in.
+    pub fn set_caches(&mut self, v: Option<Vec<CacheKind>>) {
+        self.caches = v;
+    }
+
+    pub fn is_cache_enabled(&self, cache: CacheKind) -> bool {
+        self.caches
+            .as_ref()
+            .is_none_or(|caches| caches.contains(&cache))
+    }
+
+    /// Sets the reference policies to run in addition to the caches.
+    pub fn set_policies(&mut self, v: Vec<ReferencePolicy>) {
+        self.policies = v;
+    }
+
+    /// Computes the miss ratio curve of each trace with the method. If `points` is
+    /// given, the caches are run at that number of capacities selected on the
+    /// curve. Otherwise, the caches are not run.
+    pub fn set_mrc(&mut self, method: Option<MrcMethod>, points: Option<usize>) {
+        self.mrc = method;
+        self.mrc_points = points;
+    }
+
     pub fn set_eviction_listener(&mut self, v: RemovalNotificationMode) {
         self.eviction_listener = v;
     }
@@ -77,6 +284,71 @@ impl Config {
         self.size_aware = v;
     }
 
+    /// Replays each entry of the traces as one key weighted by the length of its
+    /// range, instead of a key for each block in the range. It also enables the
+    /// size-aware mode, and the capacities become the total weight.
+    pub fn set_weighted_ranges(&mut self, v: bool) {
+        self.weighted_ranges = v;
+        if v {
+            self.size_aware = true;
+        }
+    }
+
+    /// Draws the weights of the values from the distribution instead of hashing
+    /// the request ids. It also enables the size-aware mode.
+    pub fn set_weight_dist(&mut self, v: Option<WeightDist>, seed: u64) {
+        if v.is_some() {
+            self.size_aware = true;
+        }
+        self.weight_dist = v;
+        self.weight_seed = seed;
+    }
+
+    /// Reads the weights of the values from the column of the trace files. The
+    /// lines without the column fall back to the other weights. It also enables
+    /// the size-aware mode.
+    pub fn set_weight_column(&mut self, v: Option<usize>) {
+        if v.is_some() {
+            self.size_aware = true;
+        }
+        self.weight_column = v;
+    }
+
+    /// Returns the max capacity of the caches for the capacity of the trace. In the
+    /// size-aware mode, it is multiplied by the mean weight of the values, unless
+    /// the capacities are in bytes or the weights are the lengths of the ranges.
+    pub fn max_capacity(&self, capacity: usize) -> u64 {
+        if self.size_aware && !self.capacities_in_bytes && !self.weighted_ranges {
+            (capacity as f64 * self.mean_weight()).round() as u64
+        } else {
+            capacity as u64
+        }
+    }
+
+    /// Returns the number of entries to preallocate the caches for. With the
+    /// capacities in bytes, it is estimated from the mean weight of the values.
+    pub fn initial_capacity(&self, capacity: usize) -> usize {
+        if self.capacities_in_bytes && !self.weighted_ranges {
+            ((capacity as f64 / self.mean_weight()) as usize).max(1)
+        } else {
+            capacity
+        }
+    }
+
+    fn mean_weight(&self) -> f64 {
+        self.weight_dist
+            .as_ref()
+            .map_or(DEFAULT_MEAN_WEIGHT, WeightDist::mean)
+    }
+
+    /// Sets the type of the values and their size in bytes. The values are
+    /// allocated on insert, and cloned and dropped by the caches, so these costs are
+    /// included in the results.
+    pub fn set_value(&mut self, kind: ValueKind, size: usize) {
+        self.value_kind = kind;
+        self.value_size = size;
+    }
+
     pub fn set_entry_api(&mut self, v: bool) {
         self.entry_api = v;
     }
@@ -90,9 +362,400 @@ impl Config {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum RemovalNotificationMode {
     None,
     Immediate,
     Queued,
 }
+
+/// The types of the values stored in the caches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ValueKind {
+    /// An `Arc<[u8]>`, cloned by incrementing the reference count.
+    ArcSlice,
+    /// A `Vec<u8>`, cloned by copying the bytes.
+    Vec,
+    /// A `String`, cloned by copying the bytes.
+    String,
+    /// A struct with several heap-allocated fields, cloned and dropped field by
+    /// field.
+    Struct,
+}
+
+impl ValueKind {
+    pub const ALL: [ValueKind; 4] = [Self::ArcSlice, Self::Vec, Self::String, Self::Struct];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ArcSlice => "arc-slice",
+            Self::Vec => "vec",
+            Self::String => "string",
+            Self::Struct => "struct",
+        }
+    }
+}
+
+impl TryFrom<&str> for ValueKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim().to_lowercase();
+        Self::ALL
+            .iter()
+            .find(|k| k.name() == value)
+            .copied()
+            .ok_or_else(|| {
+                let names = Self::ALL.iter().map(|k| k.name()).collect::<Vec<_>>();
+                anyhow::anyhow!(
+                    r#"Unknown value kind "{}". It must be one of {}"#,
+                    value,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// The classic replacement policies with simple single-threaded implementations,
+/// used as references to compare the hit ratios with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReferencePolicy {
+    Lru,
+    Lfu,
+    Arc,
+    Lirs,
+    WTinyLfu,
+    Random,
+}
+
+impl ReferencePolicy {
+    pub const ALL: [ReferencePolicy; 6] = [
+        Self::Lru,
+        Self::Lfu,
+        Self::Arc,
+        Self::Lirs,
+        Self::WTinyLfu,
+        Self::Random,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lru => "lru",
+            Self::Lfu => "lfu",
+            Self::Arc => "arc",
+            Self::Lirs => "lirs",
+            Self::WTinyLfu => "w-tinylfu",
+            Self::Random => "random",
+        }
+    }
+
+    /// The name shown in the reports.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Lru => "LRU (reference)",
+            Self::Lfu => "LFU (reference)",
+            Self::Arc => "ARC (reference)",
+            Self::Lirs => "LIRS (reference)",
+            Self::WTinyLfu => "W-TinyLFU (reference)",
+            Self::Random => "Random (reference)",
+        }
+    }
+}
+
+impl TryFrom<&str> for ReferencePolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim().to_lowercase();
+        Self::ALL
+            .iter()
+            .find(|p| p.name() == value)
+            .copied()
+            .ok_or_else(|| {
+                let names = Self::ALL.iter().map(|p| p.name()).collect::<Vec<_>>();
+                anyhow::anyhow!(
+                    r#"Unknown policy "{}". It must be one of {} or all"#,
+                    value,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// The versions of Moka that can be compiled in. Moka v0.12 and a local checkout
+/// can be compiled in together with one of the older versions to run them side by
+/// side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MokaVersion {
+    V08,
+    V09,
+    V010,
+    V011,
+    V012,
+    /// The Moka checkout linked at `moka-local/checkout`.
+    Local,
+}
+
+impl MokaVersion {
+    pub const ALL: [MokaVersion; 6] = [
+        Self::V08,
+        Self::V09,
+        Self::V010,
+        Self::V011,
+        Self::V012,
+        Self::Local,
+    ];
+
+    /// The version to show in the reports, e.g. `0.12`. For the local checkout, it
+    /// has the version and git commit of the checkout, e.g. `0.12.9, local 1a2b3c4`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::V08 => "0.8",
+            Self::V09 => "0.9",
+            Self::V010 => "0.10",
+            Self::V011 => "0.11",
+            Self::V012 => "0.12",
+            Self::Local => match option_env!("MOKA_LOCAL_LABEL") {
+                Some(label) => label,
+                None => "local",
+            },
+        }
+    }
+
+    /// The crate feature to compile the version in.
+    pub fn feature(&self) -> &'static str {
+        match self {
+            Self::V08 => "moka-v08",
+            Self::V09 => "moka-v09",
+            Self::V010 => "moka-v010",
+            Self::V011 => "moka-v011",
+            Self::V012 => "moka-v012",
+            Self::Local => "moka-local",
+        }
+    }
+
+    pub fn is_compiled_in(&self) -> bool {
+        match self {
+            Self::V08 => cfg!(feature = "moka-v08"),
+            Self::V09 => cfg!(feature = "moka-v09"),
+            Self::V010 => cfg!(feature = "moka-v010"),
+            Self::V011 => cfg!(feature = "moka-v011"),
+            Self::V012 => cfg!(feature = "moka-v012"),
+            Self::Local => cfg!(feature = "moka-local"),
+        }
+    }
+
+    /// Returns the compiled-in versions from the oldest.
+    pub fn compiled_in() -> impl Iterator<Item = Self> {
+        Self::ALL.into_iter().filter(|v| v.is_compiled_in())
+    }
+}
+
+impl std::fmt::Display for MokaVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// The cache drivers in the order they are run. Some of them are compiled in only
+/// when their crate features are enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheKind {
+    /// Mini Moka's (or Moka v0.8 and v0.9's) `unsync::Cache`.
+    MokaUnsync,
+    HashLink,
+    QuickCache,
+    Stretto,
+    TinyUfo,
+    /// Mini Moka's `sync::Cache` (or Moka v0.8 and v0.9's `dash::Cache`).
+    MokaDash,
+    MokaSync(MokaVersion),
+    MokaAsync(MokaVersion),
+    MokaSegmented(MokaVersion),
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 24] = [
+        Self::MokaUnsync,
+        Self::HashLink,
+        Self::QuickCache,
+        Self::Stretto,
+        Self::TinyUfo,
+        Self::MokaDash,
+        Self::MokaSync(MokaVersion::V08),
+        Self::MokaSync(MokaVersion::V09),
+        Self::MokaSync(MokaVersion::V010),
+        Self::MokaSync(MokaVersion::V011),
+        Self::MokaSync(MokaVersion::V012),
+        Self::MokaSync(MokaVersion::Local),
+        Self::MokaAsync(MokaVersion::V08),
+        Self::MokaAsync(MokaVersion::V09),
+        Self::MokaAsync(MokaVersion::V010),
+        Self::MokaAsync(MokaVersion::V011),
+        Self::MokaAsync(MokaVersion::V012),
+        Self::MokaAsync(MokaVersion::Local),
+        Self::MokaSegmented(MokaVersion::V08),
+        Self::MokaSegmented(MokaVersion::V09),
+        Self::MokaSegmented(MokaVersion::V010),
+        Self::MokaSegmented(MokaVersion::V011),
+        Self::MokaSegmented(MokaVersion::V012),
+        Self::MokaSegmented(MokaVersion::Local),
+    ];
+
+    /// The names of the Moka caches that select all compiled-in versions, e.g.
+    /// `moka-sync` for `moka-v011-sync` and `moka-v012-sync`.
+    const MOKA_ALIASES: [&'static str; 3] = ["moka-sync", "moka-async", "moka-segmented"];
+
+    fn from_moka_alias(name: &str) -> Option<fn(MokaVersion) -> Self> {
+        match name {
+            "moka-sync" => Some(Self::MokaSync),
+            "moka-async" => Some(Self::MokaAsync),
+            "moka-segmented" => Some(Self::MokaSegmented),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        use MokaVersion::*;
+
+        let mini_moka = cfg!(feature = "mini-moka");
+        match self {
+            Self::MokaUnsync if mini_moka => "mini-moka-unsync",
+            Self::MokaUnsync => "moka-unsync",
+            Self::HashLink => "hashlink",
+            Self::QuickCache => "quick_cache",
+            Self::Stretto => "stretto",
+            Self::TinyUfo => "tiny-ufo",
+            Self::MokaDash if mini_moka => "mini-moka-sync",
+            Self::MokaDash => "moka-dash",
+            Self::MokaSync(V08) => "moka-v08-sync",
+            Self::MokaSync(V09) => "moka-v09-sync",
+            Self::MokaSync(V010) => "moka-v010-sync",
+            Self::MokaSync(V011) => "moka-v011-sync",
+            Self::MokaSync(V012) => "moka-v012-sync",
+            Self::MokaSync(Local) => "moka-local-sync",
+            Self::MokaAsync(V08) => "moka-v08-async",
+            Self::MokaAsync(V09) => "moka-v09-async",
+            Self::MokaAsync(V010) => "moka-v010-async",
+            Self::MokaAsync(V011) => "moka-v011-async",
+            Self::MokaAsync(V012) => "moka-v012-async",
+            Self::MokaAsync(Local) => "moka-local-async",
+            Self::MokaSegmented(V08) => "moka-v08-segmented",
+            Self::MokaSegmented(V09) => "moka-v09-segmented",
+            Self::MokaSegmented(V010) => "moka-v010-segmented",
+            Self::MokaSegmented(V011) => "moka-v011-segmented",
+            Self::MokaSegmented(V012) => "moka-v012-segmented",
+            Self::MokaSegmented(Local) => "moka-local-segmented",
+        }
+    }
+
+    /// The crate feature to enable the cache.
+    pub fn feature(&self) -> &'static str {
+        match self {
+            Self::MokaUnsync | Self::MokaDash => "mini-moka",
+            Self::HashLink => "hashlink",
+            Self::QuickCache => "quick_cache",
+            Self::Stretto => "stretto",
+            Self::TinyUfo => "tiny-ufo",
+            Self::MokaSync(v) | Self::MokaAsync(v) | Self::MokaSegmented(v) => v.feature(),
+        }
+    }
+
+    /// Parses the cache names. `all` selects all caches compiled in, and
+    /// `moka-sync`, `moka-async` and `moka-segmented` select the caches of all
+    /// compiled-in Moka versions.
+    pub fn parse_list<'a>(names: impl IntoIterator<Item = &'a str>) -> anyhow::Result<Vec<Self>> {
+        let mut caches = Vec::new();
+        for name in names {
+            let name = name.trim().to_lowercase();
+            if name == "all" {
+                caches.extend(Self::ALL.into_iter().filter(|c| c.is_compiled_in()));
+            } else if let Some(cache) = Self::from_moka_alias(&name) {
+                caches.extend(MokaVersion::compiled_in().map(cache));
+            } else {
+                caches.push(Self::try_from(name.as_str())?);
+            }
+        }
+        Ok(caches)
+    }
+
+    pub fn is_compiled_in(&self) -> bool {
+        let moka_v08_or_v09 = cfg!(any(feature = "moka-v08", feature = "moka-v09"));
+        match self {
+            Self::MokaUnsync | Self::MokaDash => cfg!(feature = "mini-moka") || moka_v08_or_v09,
+            Self::HashLink => cfg!(feature = "hashlink"),
+            Self::QuickCache => cfg!(feature = "quick_cache"),
+            Self::Stretto => cfg!(feature = "stretto"),
+            Self::TinyUfo => cfg!(feature = "tiny-ufo"),
+            Self::MokaSync(v) | Self::MokaAsync(v) | Self::MokaSegmented(v) => v.is_compiled_in(),
+        }
+    }
+}
+
+impl std::fmt::Display for CacheKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for CacheKind {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl TryFrom<&str> for CacheKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim().to_lowercase();
+        let cache = Self::ALL
+            .iter()
+            .find(|c| c.name() == value)
+            .copied()
+            .ok_or_else(|| {
+                let names = Self::ALL
+                    .iter()
+                    .map(|c| c.name())
+                    .chain(Self::MOKA_ALIASES)
+                    .collect::<Vec<_>>();
+                anyhow::anyhow!(
+                    r#"Unknown cache "{}". It must be one of {} or all"#,
+                    value,
+                    names.join(", ")
+                )
+            })?;
+        if !cache.is_compiled_in() {
+            anyhow::bail!(
+                r#"The cache "{}" is not compiled in. Enable the crate feature "{}""#,
+                value,
+                cache.feature()
+            );
+        }
+        Ok(cache)
+    }
+}
+
+fn serialize_display<T: std::fmt::Display, S: Serializer>(v: &T, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(v)
+}
+
+fn serialize_display_opt<T: std::fmt::Display, S: Serializer>(
+    v: &Option<T>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match v {
+        Some(v) => s.collect_str(v),
+        None => s.serialize_none(),
+    }
+}
+
+fn serialize_secs<S: Serializer>(v: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    match v {
+        Some(d) => s.serialize_f64(d.as_secs_f64()),
+        None => s.serialize_none(),
+    }
+}
@@ -1,21 +1,82 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 
-#[cfg(not(feature = "moka-v08"))]
-use crate::moka::notification::RemovalCause;
+/// The cause of the removal of an entry. It is converted from the `RemovalCause`
+/// of each compiled-in Moka version.
+#[cfg_attr(
+    all(
+        feature = "moka-v08",
+        not(any(feature = "moka-v012", feature = "moka-local"))
+    ),
+    allow(dead_code)
+)]
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum RemovalCause {
+    Size,
+    Expired,
+    Explicit,
+    Replaced,
+}
+
+// Moka v0.8 has no `RemovalCause`.
+#[cfg_attr(
+    all(
+        feature = "moka-v08",
+        not(any(feature = "moka-v012", feature = "moka-local"))
+    ),
+    allow(unused_macros)
+)]
+macro_rules! impl_from_removal_cause {
+    ($moka:ident) => {
+        impl From<$moka::notification::RemovalCause> for RemovalCause {
+            fn from(cause: $moka::notification::RemovalCause) -> Self {
+                use $moka::notification::RemovalCause as Cause;
+                match cause {
+                    Cause::Size => Self::Size,
+                    Cause::Expired => Self::Expired,
+                    Cause::Explicit => Self::Explicit,
+                    Cause::Replaced => Self::Replaced,
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "moka-v09")]
+impl_from_removal_cause!(moka09);
+#[cfg(feature = "moka-v010")]
+impl_from_removal_cause!(moka010);
+#[cfg(feature = "moka-v011")]
+impl_from_removal_cause!(moka011);
+#[cfg(feature = "moka-v012")]
+impl_from_removal_cause!(moka012);
+#[cfg(feature = "moka-local")]
+impl_from_removal_cause!(mokalocal);
 
 #[derive(Default)]
 pub(crate) struct EvictionCounters {
     size: AtomicU64,
     expired: AtomicU64,
     explicit: AtomicU64,
-    #[cfg_attr(feature = "moka-v08", allow(dead_code))]
+    #[cfg_attr(
+        all(
+            feature = "moka-v08",
+            not(any(feature = "moka-v012", feature = "moka-local"))
+        ),
+        allow(dead_code)
+    )]
     replaced: AtomicU64,
 }
 
 impl EvictionCounters {
-    #[cfg(not(feature = "moka-v08"))]
-    pub(crate) fn increment(&self, cause: RemovalCause) {
-        match cause {
+    #[cfg_attr(
+        all(
+            feature = "moka-v08",
+            not(any(feature = "moka-v012", feature = "moka-local"))
+        ),
+        allow(dead_code)
+    )]
+    pub(crate) fn increment(&self, cause: impl Into<RemovalCause>) {
+        match cause.into() {
             RemovalCause::Size => self.size.fetch_add(1, Ordering::AcqRel),
             RemovalCause::Expired => self.expired.fetch_add(1, Ordering::AcqRel),
             RemovalCause::Explicit => self.explicit.fetch_add(1, Ordering::AcqRel),
@@ -0,0 +1,282 @@
+//! Synthetic workload generators. They produce `TraceEntry` streams from
+//! parameterized key distributions, so the caches can be benchmarked without
+//! creating trace files.
+
+use std::fmt;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::Zipf;
+
+use crate::parser::TraceEntry;
+
+pub const DEFAULT_KEY_SPACE: usize = 1_000_000;
+pub const DEFAULT_NUM_OPS: usize = 10_000_000;
+pub const DEFAULT_SKEW: f64 = 0.99;
+pub const DEFAULT_HOT_KEY_FRACTION: f64 = 0.2;
+pub const DEFAULT_HOT_OP_FRACTION: f64 = 0.8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    /// Keys are chosen by a Zipfian distribution. Key `0` is the most popular one.
+    Zipf { skew: f64 },
+    /// Keys are chosen uniformly from the key space.
+    Uniform,
+    /// `hot_op_fraction` of the operations go to the first `hot_key_fraction` of
+    /// the key space, and the rest go to the remaining keys. Keys are chosen
+    /// uniformly within each set.
+    Hotspot {
+        hot_key_fraction: f64,
+        hot_op_fraction: f64,
+    },
+    /// Keys are scanned sequentially, wrapping around at the end of the key space.
+    Scan,
+    /// New keys are added over the run, and the most recently added keys are the
+    /// most popular ones, following a Zipfian distribution.
+    Latest { skew: f64 },
+}
+
+impl Distribution {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Zipf { .. } => "zipf",
+            Self::Uniform => "uniform",
+            Self::Hotspot { .. } => "hotspot",
+            Self::Scan => "scan",
+            Self::Latest { .. } => "latest",
+        }
+    }
+}
+
+/// Parameters of a synthetic workload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Workload {
+    pub distribution: Distribution,
+    pub key_space: usize,
+    pub num_ops: usize,
+    pub seed: u64,
+    default_capacities: Vec<usize>,
+}
+
+impl Workload {
+    pub fn new(distribution: Distribution, key_space: usize, num_ops: usize, seed: u64) -> Self {
+        // 1%, 5% and 10% of the key space.
+        let default_capacities = [100, 20, 10]
+            .iter()
+            .map(|d| (key_space / d).max(1))
+            .collect();
+
+        Self {
+            distribution,
+            key_space,
+            num_ops,
+            seed,
+            default_capacities,
+        }
+    }
+
+    /// Creates a workload from a distribution name (e.g. "zipf").
+    pub fn parse(
+        name: &str,
+        skew: f64,
+        hot_key_fraction: f64,
+        hot_op_fraction: f64,
+        key_space: usize,
+        num_ops: usize,
+        seed: u64,
+    ) -> anyhow::Result<Self> {
+        let distribution = match name.trim().to_lowercase().as_str() {
+            "zipf" => Distribution::Zipf { skew },
+            "uniform" => Distribution::Uniform,
+            "hotspot" => Distribution::Hotspot {
+                hot_key_fraction,
+                hot_op_fraction,
+            },
+            "scan" => Distribution::Scan,
+            "latest" => Distribution::Latest { skew },
+            _ => anyhow::bail!(
+                r#"Unknown generator "{}". It must be one of "zipf", "uniform", "hotspot", "scan" or "latest""#,
+                name
+            ),
+        };
+
+        if key_space == 0 {
+            anyhow::bail!("The key space of a generator must be greater than zero");
+        }
+        if let Distribution::Zipf { skew } | Distribution::Latest { skew } = distribution {
+            if skew.is_nan() || skew < 0.0 {
+                anyhow::bail!("The skew must be zero or a positive number, but got {skew}");
+            }
+        }
+        if let Distribution::Hotspot {
+            hot_key_fraction,
+            hot_op_fraction,
+        } = distribution
+        {
+            if !(0.0..=1.0).contains(&hot_key_fraction) || !(0.0..=1.0).contains(&hot_op_fraction) {
+                anyhow::bail!("The hot key and hot operation fractions must be between 0 and 1");
+            }
+        }
+
+        Ok(Self::new(distribution, key_space, num_ops, seed))
+    }
+
+    /// Creates the workloads of a distribution name, one for each of the `skews`
+    /// if the distribution uses the skew (zipf and latest). Otherwise, creates
+    /// only one workload.
+    pub fn parse_each_skew(
+        name: &str,
+        skews: &[f64],
+        hot_key_fraction: f64,
+        hot_op_fraction: f64,
+        key_space: usize,
+        num_ops: usize,
+        seed: u64,
+    ) -> anyhow::Result<Vec<Self>> {
+        let mut workloads = Vec::new();
+        for &skew in skews {
+            let workload = Self::parse(
+                name,
+                skew,
+                hot_key_fraction,
+                hot_op_fraction,
+                key_space,
+                num_ops,
+                seed,
+            )?;
+            let uses_skew = matches!(
+                workload.distribution,
+                Distribution::Zipf { .. } | Distribution::Latest { .. }
+            );
+            workloads.push(workload);
+            if !uses_skew {
+                break;
+            }
+        }
+        Ok(workloads)
+    }
+
+    pub fn default_capacities(&self) -> &[usize] {
+        &self.default_capacities
+    }
+
+    pub(crate) fn generate(&self) -> WorkloadGenerator {
+        WorkloadGenerator::new(self)
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.distribution.name())?;
+        match self.distribution {
+            Distribution::Zipf { skew } | Distribution::Latest { skew } => {
+                write!(f, "skew={skew}, ")?
+            }
+            Distribution::Hotspot {
+                hot_key_fraction,
+                hot_op_fraction,
+            } => write!(
+                f,
+                "hot-keys={hot_key_fraction}, hot-ops={hot_op_fraction}, "
+            )?,
+            Distribution::Uniform | Distribution::Scan => (),
+        }
+        write!(
+            f,
+            "keys={}, ops={}, seed={})",
+            self.key_space, self.num_ops, self.seed
+        )
+    }
+}
+
+/// An iterator of `TraceEntry`s. Each entry has a single key, and its line number
+/// is the index of the operation.
+pub(crate) struct WorkloadGenerator {
+    distribution: Distribution,
+    key_space: usize,
+    num_ops: usize,
+    rng: StdRng,
+    zipf: Option<Zipf<f64>>,
+    op_index: usize,
+    // For the scan distribution, the next key. For the latest distribution, the
+    // most recently added key.
+    cursor: usize,
+}
+
+impl WorkloadGenerator {
+    fn new(workload: &Workload) -> Self {
+        let zipf = match workload.distribution {
+            Distribution::Zipf { skew } | Distribution::Latest { skew } => {
+                Some(Zipf::new(workload.key_space as u64, skew).expect("Invalid Zipf parameters"))
+            }
+            _ => None,
+        };
+
+        Self {
+            distribution: workload.distribution,
+            key_space: workload.key_space,
+            num_ops: workload.num_ops,
+            rng: StdRng::seed_from_u64(workload.seed),
+            zipf,
+            op_index: 0,
+            cursor: 0,
+        }
+    }
+
+    fn next_key(&mut self) -> usize {
+        match self.distribution {
+            Distribution::Zipf { .. } => self.sample_zipf() - 1,
+            Distribution::Uniform => self.rng.gen_range(0..self.key_space),
+            Distribution::Hotspot {
+                hot_key_fraction,
+                hot_op_fraction,
+            } => {
+                let hot_keys =
+                    ((self.key_space as f64 * hot_key_fraction) as usize).clamp(1, self.key_space);
+                let is_hot = hot_keys == self.key_space || self.rng.gen_bool(hot_op_fraction);
+                if is_hot {
+                    self.rng.gen_range(0..hot_keys)
+                } else {
+                    self.rng.gen_range(hot_keys..self.key_space)
+                }
+            }
+            Distribution::Scan => {
+                let key = self.cursor;
+                self.cursor = (self.cursor + 1) % self.key_space;
+                key
+            }
+            Distribution::Latest { .. } => {
+                // Add new keys at a constant rate, so that the whole key space is
+                // added by the end of the run.
+                let latest = if self.num_ops == 0 {
+                    self.key_space - 1
+                } else {
+                    (self.op_index as u128 * self.key_space as u128 / self.num_ops as u128) as usize
+                };
+                self.cursor = latest.min(self.key_space - 1);
+                let offset = (self.sample_zipf() - 1) % (self.cursor + 1);
+                self.cursor - offset
+            }
+        }
+    }
+
+    /// Returns a rank in `1..=key_space`.
+    fn sample_zipf(&mut self) -> usize {
+        let zipf = self.zipf.as_ref().expect("zipf is not initialized");
+        let rank = self.rng.sample(zipf) as usize;
+        rank.clamp(1, self.key_space)
+    }
+}
+
+impl Iterator for WorkloadGenerator {
+    type Item = TraceEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.op_index >= self.num_ops {
+            return None;
+        }
+        let key = self.next_key();
+        let entry = TraceEntry::new(key..(key + 1), self.op_index);
+        self.op_index += 1;
+        Some(entry)
+    }
+}
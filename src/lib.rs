@@ -1,58 +1,62 @@
-#[cfg(all(
+// `is_multiple_of` is stable only since Rust 1.87.
+#![allow(clippy::manual_is_multiple_of)]
+
+#[cfg(not(any(
     feature = "moka-v012",
-    any(
+    feature = "moka-local",
+    feature = "moka-v011",
+    feature = "moka-v010",
+    feature = "moka-v09",
+    feature = "moka-v08"
+)))]
+compile_error!("You must enable at least one of `moka-v012`, `moka-local`, `moka-v011`, `moka-v010`, `moka-v09` and `moka-v08` features.");
+
+#[cfg(any(
+    all(
         feature = "moka-v011",
-        feature = "moka-v010",
-        feature = "moka-v09",
-        feature = "moka-v08"
-    )
+        any(feature = "moka-v010", feature = "moka-v09", feature = "moka-v08")
+    ),
+    all(feature = "moka-v010", any(feature = "moka-v09", feature = "moka-v08")),
+    all(feature = "moka-v09", feature = "moka-v08")
 ))]
 compile_error!(
-    "You cannot enable `moka-v011`, `moka-v010`, `moka-v09` and/or `moka-v8` features while `moka-v012` is enabled.\n\
-                You might need `--no-default-features`."
+    "You cannot enable more than one of `moka-v011`, `moka-v010`, `moka-v09` and `moka-v08` features. \
+    One of them can be enabled together with `moka-v012`.\n\
+    You might need `--no-default-features`."
 );
 
-use std::io::prelude::*;
 use std::sync::Arc;
-use std::{fs::File, io::BufReader, time::Instant};
-
-#[cfg(feature = "moka-v012")]
-pub(crate) use moka012 as moka;
-
-#[cfg(feature = "moka-v011")]
-pub(crate) use moka011 as moka;
-
-#[cfg(feature = "moka-v010")]
-pub(crate) use moka010 as moka;
-
-#[cfg(feature = "moka-v09")]
-pub(crate) use moka09 as moka;
-
-#[cfg(feature = "moka-v08")]
-pub(crate) use moka08 as moka;
+use std::time::{Duration, Instant};
 
+pub mod analyze;
 mod async_rt_helper;
+pub mod binary_trace;
 mod cache;
+pub mod compare;
 pub mod config;
 mod eviction_counters;
+pub mod generator;
 mod load_gen;
+pub mod mrc;
+pub mod op_mix;
+pub mod opt;
+pub mod output;
 mod parser;
 mod report;
-mod trace_file;
+pub mod scenario;
+pub mod trace_file;
+pub mod weight;
 
+pub use cache::Capabilities;
 pub(crate) use eviction_counters::EvictionCounters;
-pub use report::Report;
+pub use report::{IterationStats, Latencies, LatencySummary, Report, StreamingStats, Summary};
 pub use trace_file::TraceFile;
 
 use async_rt_helper as rt;
-use cache::{
-    moka_driver::{
-        async_cache::MokaAsyncCache, sync_cache::MokaSyncCache, sync_segmented::MokaSegmentedCache,
-    },
-    AsyncCacheDriver, CacheDriver,
-};
-use config::Config;
-use itertools::Itertools;
+use cache::{policy::PolicyCache, AsyncCacheDriver, CacheDriver};
+use config::{CacheKind, Config, MokaVersion, ReferencePolicy};
+use load_gen::CommandSource;
+use op_mix::Operation;
 use parser::TraceEntry;
 use report::ReportBuilder;
 
@@ -69,8 +73,7 @@ use crate::cache::stretto::StrettoCache;
 #[cfg(feature = "tiny-ufo")]
 use crate::cache::tiny_ufo::TinyUfoCache;
 
-const BATCH_SIZE: usize = 200;
-
+#[derive(Clone)]
 pub(crate) enum Command {
     GetOrInsert(TraceEntry),
     GetOrInsertOnce(TraceEntry),
@@ -81,73 +84,346 @@ pub(crate) enum Command {
     Iterate,
 }
 
+impl Command {
+    pub(crate) fn operation(&self) -> Operation {
+        match self {
+            Self::GetOrInsert(_) => Operation::Get,
+            Self::GetOrInsertOnce(_) => Operation::GetWith,
+            Self::Update(_) => Operation::Update,
+            Self::Invalidate(_) => Operation::Invalidate,
+            Self::InvalidateAll => Operation::InvalidateAll,
+            Self::InvalidateEntriesIf(_) => Operation::InvalidateEntriesIf,
+            Self::Iterate => Operation::Iterate,
+        }
+    }
+}
+
+/// Returns the capabilities of the cache, or `None` if it is not compiled in.
+pub fn cache_capabilities(cache: CacheKind) -> Option<Capabilities> {
+    let capabilities = match cache {
+        #[cfg(any(feature = "mini-moka", feature = "moka-v08", feature = "moka-v09"))]
+        CacheKind::MokaUnsync => <MiniMokaUnsyncCache as CacheDriver<TraceEntry>>::CAPABILITIES,
+        #[cfg(feature = "hashlink")]
+        CacheKind::HashLink => <HashLink as CacheDriver<TraceEntry>>::CAPABILITIES,
+        #[cfg(feature = "quick_cache")]
+        CacheKind::QuickCache => <QuickCache as CacheDriver<TraceEntry>>::CAPABILITIES,
+        #[cfg(feature = "stretto")]
+        CacheKind::Stretto => <StrettoCache as CacheDriver<TraceEntry>>::CAPABILITIES,
+        #[cfg(feature = "tiny-ufo")]
+        CacheKind::TinyUfo => <TinyUfoCache as CacheDriver<TraceEntry>>::CAPABILITIES,
+        #[cfg(any(feature = "mini-moka", feature = "moka-v08", feature = "moka-v09"))]
+        CacheKind::MokaDash => <MiniMokSyncCache as CacheDriver<TraceEntry>>::CAPABILITIES,
+        CacheKind::MokaSync(v) | CacheKind::MokaAsync(v) | CacheKind::MokaSegmented(v)
+            if v.is_compiled_in() =>
+        {
+            moka_capabilities(cache)
+        }
+        #[allow(unreachable_patterns)]
+        _ => return None,
+    };
+    Some(capabilities)
+}
+
+/// Returns the capabilities of a Moka cache of a compiled-in version.
+fn moka_capabilities(cache: CacheKind) -> Capabilities {
+    #[cfg(feature = "moka-local")]
+    use cache::moka_driver_local as local;
+    #[cfg(any(
+        feature = "moka-v08",
+        feature = "moka-v09",
+        feature = "moka-v010",
+        feature = "moka-v011"
+    ))]
+    use cache::moka_driver_v1 as v1;
+    #[cfg(feature = "moka-v012")]
+    use cache::moka_driver_v2 as v2;
+
+    match cache {
+        #[cfg(feature = "moka-v012")]
+        CacheKind::MokaSync(MokaVersion::V012) => {
+            <v2::sync_cache::MokaSyncCache<v2::sync_cache::GetWith> as CacheDriver<TraceEntry>>::CAPABILITIES
+        }
+        #[cfg(feature = "moka-v012")]
+        CacheKind::MokaAsync(MokaVersion::V012) => {
+            <v2::async_cache::MokaAsyncCache<v2::async_cache::GetWith> as AsyncCacheDriver<TraceEntry>>::CAPABILITIES
+        }
+        #[cfg(feature = "moka-v012")]
+        CacheKind::MokaSegmented(MokaVersion::V012) => {
+            <v2::sync_segmented::MokaSegmentedCache<v2::sync_segmented::GetWith> as CacheDriver<TraceEntry>>::CAPABILITIES
+        }
+        #[cfg(feature = "moka-local")]
+        CacheKind::MokaSync(MokaVersion::Local) => {
+            <local::sync_cache::MokaSyncCache<local::sync_cache::GetWith> as CacheDriver<TraceEntry>>::CAPABILITIES
+        }
+        #[cfg(feature = "moka-local")]
+        CacheKind::MokaAsync(MokaVersion::Local) => {
+            <local::async_cache::MokaAsyncCache<local::async_cache::GetWith> as AsyncCacheDriver<TraceEntry>>::CAPABILITIES
+        }
+        #[cfg(feature = "moka-local")]
+        CacheKind::MokaSegmented(MokaVersion::Local) => {
+            <local::sync_segmented::MokaSegmentedCache<local::sync_segmented::GetWith> as CacheDriver<TraceEntry>>::CAPABILITIES
+        }
+        // The other compiled-in version, if any, is one of the older versions.
+        #[cfg(any(
+            feature = "moka-v08",
+            feature = "moka-v09",
+            feature = "moka-v010",
+            feature = "moka-v011"
+        ))]
+        CacheKind::MokaSync(_) => {
+            <v1::sync_cache::MokaSyncCache<v1::sync_cache::GetWith> as CacheDriver<TraceEntry>>::CAPABILITIES
+        }
+        #[cfg(any(
+            feature = "moka-v08",
+            feature = "moka-v09",
+            feature = "moka-v010",
+            feature = "moka-v011"
+        ))]
+        CacheKind::MokaAsync(_) => {
+            <v1::async_cache::MokaAsyncCache<v1::async_cache::GetWith> as AsyncCacheDriver<TraceEntry>>::CAPABILITIES
+        }
+        #[cfg(any(
+            feature = "moka-v08",
+            feature = "moka-v09",
+            feature = "moka-v010",
+            feature = "moka-v011"
+        ))]
+        CacheKind::MokaSegmented(_) => {
+            <v1::sync_segmented::MokaSegmentedCache<v1::sync_segmented::GetWith> as CacheDriver<TraceEntry>>::CAPABILITIES
+        }
+        _ => unreachable!("{cache} is not a Moka cache of a compiled-in version"),
+    }
+}
+
+/// Returns the capabilities shared by all reference policies.
+pub fn reference_policy_capabilities() -> Capabilities {
+    <PolicyCache as CacheDriver<TraceEntry>>::CAPABILITIES
+}
+
+/// Runs the benchmark `config.warmup_iterations` times and discards the reports,
+/// and then runs it `config.iterations` times. Returns the report with the
+/// statistics over the measured iterations.
+pub fn run_iterations(
+    config: &Config,
+    mut run: impl FnMut() -> anyhow::Result<Report>,
+) -> anyhow::Result<Report> {
+    for _ in 0..config.warmup_iterations {
+        run()?;
+    }
+    let reports = (0..config.iterations.max(1))
+        .map(|_| run())
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(Report::from_iterations(
+        reports,
+        config.warmup_iterations,
+        config.max_cv,
+    ))
+}
+
+/// The async version of `run_iterations`.
+pub async fn run_iterations_async<F, Fut>(config: &Config, mut run: F) -> anyhow::Result<Report>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<Report>>,
+{
+    for _ in 0..config.warmup_iterations {
+        run().await?;
+    }
+    let mut reports = Vec::with_capacity(config.iterations.max(1));
+    for _ in 0..config.iterations.max(1) {
+        reports.push(run().await?);
+    }
+    Ok(Report::from_iterations(
+        reports,
+        config.warmup_iterations,
+        config.max_cv,
+    ))
+}
+
 pub fn run_multi_threads_moka_sync(
     config: &Config,
+    version: MokaVersion,
     capacity: usize,
     num_clients: u16,
 ) -> anyhow::Result<Report> {
-    let max_cap = if config.size_aware {
-        capacity as u64 * 2u64.pow(15)
-    } else {
-        capacity as u64
-    };
-    let report_builder = ReportBuilder::new("Moka Sync Cache", max_cap, Some(num_clients));
+    let max_cap = config.max_capacity(capacity);
+    let init_cap = config.initial_capacity(capacity);
+    let mut report_builder = ReportBuilder::new("Moka Sync Cache", max_cap, Some(num_clients));
+    report_builder.set_version(version.label());
+
+    match version {
+        #[cfg(feature = "moka-v012")]
+        MokaVersion::V012 => {
+            use cache::moka_driver_v2::sync_cache::MokaSyncCache;
+
+            if config.entry_api {
+                let cache_driver = MokaSyncCache::with_entry_api(config, max_cap, init_cap);
+                return run_multi_threads(config, num_clients, cache_driver, report_builder);
+            }
+
+            let cache_driver = MokaSyncCache::new(config, max_cap, init_cap);
+            run_multi_threads(config, num_clients, cache_driver, report_builder)
+        }
+        #[cfg(feature = "moka-local")]
+        MokaVersion::Local => {
+            use cache::moka_driver_local::sync_cache::MokaSyncCache;
 
-    #[cfg(not(any(feature = "moka-v08", feature = "moka-v09")))]
-    if config.entry_api {
-        let cache_driver = MokaSyncCache::with_entry_api(config, max_cap, capacity);
-        return run_multi_threads(config, num_clients, cache_driver, report_builder);
-    }
+            if config.entry_api {
+                let cache_driver = MokaSyncCache::with_entry_api(config, max_cap, init_cap);
+                return run_multi_threads(config, num_clients, cache_driver, report_builder);
+            }
 
-    let cache_driver = MokaSyncCache::new(config, max_cap, capacity);
-    run_multi_threads(config, num_clients, cache_driver, report_builder)
+            let cache_driver = MokaSyncCache::new(config, max_cap, init_cap);
+            run_multi_threads(config, num_clients, cache_driver, report_builder)
+        }
+        #[cfg(any(
+            feature = "moka-v08",
+            feature = "moka-v09",
+            feature = "moka-v010",
+            feature = "moka-v011"
+        ))]
+        v if v.is_compiled_in() => {
+            use cache::moka_driver_v1::sync_cache::MokaSyncCache;
+
+            #[cfg(not(any(feature = "moka-v08", feature = "moka-v09")))]
+            if config.entry_api {
+                let cache_driver = MokaSyncCache::with_entry_api(config, max_cap, init_cap);
+                return run_multi_threads(config, num_clients, cache_driver, report_builder);
+            }
+
+            let cache_driver = MokaSyncCache::new(config, max_cap, init_cap);
+            run_multi_threads(config, num_clients, cache_driver, report_builder)
+        }
+        _ => moka_not_compiled_in(version),
+    }
 }
 
 pub fn run_multi_threads_moka_segment(
     config: &Config,
+    version: MokaVersion,
     capacity: usize,
     num_clients: u16,
     num_segments: usize,
 ) -> anyhow::Result<Report> {
-    let max_cap = if config.size_aware {
-        capacity as u64 * 2u64.pow(15)
-    } else {
-        capacity as u64
-    };
+    let max_cap = config.max_capacity(capacity);
+    let init_cap = config.initial_capacity(capacity);
     let report_name = format!("Moka SegmentedCache({num_segments})");
-    let report_builder = ReportBuilder::new(&report_name, max_cap, Some(num_clients));
-
-    #[cfg(not(any(feature = "moka-v08", feature = "moka-v09")))]
-    if config.entry_api {
-        let cache_driver =
-            MokaSegmentedCache::with_entry_api(config, max_cap, capacity, num_segments);
-        return run_multi_threads(config, num_clients, cache_driver, report_builder);
+    let mut report_builder = ReportBuilder::new(&report_name, max_cap, Some(num_clients));
+    report_builder.set_version(version.label());
+
+    match version {
+        #[cfg(feature = "moka-v012")]
+        MokaVersion::V012 => {
+            use cache::moka_driver_v2::sync_segmented::MokaSegmentedCache;
+
+            if config.entry_api {
+                let cache_driver =
+                    MokaSegmentedCache::with_entry_api(config, max_cap, init_cap, num_segments);
+                return run_multi_threads(config, num_clients, cache_driver, report_builder);
+            }
+
+            let cache_driver = MokaSegmentedCache::new(config, max_cap, init_cap, num_segments);
+            run_multi_threads(config, num_clients, cache_driver, report_builder)
+        }
+        #[cfg(feature = "moka-local")]
+        MokaVersion::Local => {
+            use cache::moka_driver_local::sync_segmented::MokaSegmentedCache;
+
+            if config.entry_api {
+                let cache_driver =
+                    MokaSegmentedCache::with_entry_api(config, max_cap, init_cap, num_segments);
+                return run_multi_threads(config, num_clients, cache_driver, report_builder);
+            }
+
+            let cache_driver = MokaSegmentedCache::new(config, max_cap, init_cap, num_segments);
+            run_multi_threads(config, num_clients, cache_driver, report_builder)
+        }
+        #[cfg(any(
+            feature = "moka-v08",
+            feature = "moka-v09",
+            feature = "moka-v010",
+            feature = "moka-v011"
+        ))]
+        v if v.is_compiled_in() => {
+            use cache::moka_driver_v1::sync_segmented::MokaSegmentedCache;
+
+            #[cfg(not(any(feature = "moka-v08", feature = "moka-v09")))]
+            if config.entry_api {
+                let cache_driver =
+                    MokaSegmentedCache::with_entry_api(config, max_cap, init_cap, num_segments);
+                return run_multi_threads(config, num_clients, cache_driver, report_builder);
+            }
+
+            let cache_driver = MokaSegmentedCache::new(config, max_cap, init_cap, num_segments);
+            run_multi_threads(config, num_clients, cache_driver, report_builder)
+        }
+        _ => moka_not_compiled_in(version),
     }
-
-    let cache_driver = MokaSegmentedCache::new(config, max_cap, capacity, num_segments);
-    run_multi_threads(config, num_clients, cache_driver, report_builder)
 }
 
 pub async fn run_multi_tasks_moka_async(
     config: &Config,
+    version: MokaVersion,
     capacity: usize,
     num_clients: u16,
 ) -> anyhow::Result<Report> {
-    let max_cap = if config.size_aware {
-        capacity as u64 * 2u64.pow(15)
-    } else {
-        capacity as u64
-    };
-    let report_builder = ReportBuilder::new("Moka Async Cache", max_cap, Some(num_clients));
+    let max_cap = config.max_capacity(capacity);
+    let init_cap = config.initial_capacity(capacity);
+    let mut report_builder = ReportBuilder::new("Moka Async Cache", max_cap, Some(num_clients));
+    report_builder.set_version(version.label());
+
+    match version {
+        #[cfg(feature = "moka-v012")]
+        MokaVersion::V012 => {
+            use cache::moka_driver_v2::async_cache::MokaAsyncCache;
+
+            if config.entry_api {
+                let cache_driver = MokaAsyncCache::with_entry_api(config, max_cap, init_cap);
+                return run_multi_tasks(config, num_clients, cache_driver, report_builder).await;
+            }
+
+            let cache_driver = MokaAsyncCache::new(config, max_cap, init_cap);
+            run_multi_tasks(config, num_clients, cache_driver, report_builder).await
+        }
+        #[cfg(feature = "moka-local")]
+        MokaVersion::Local => {
+            use cache::moka_driver_local::async_cache::MokaAsyncCache;
 
-    #[cfg(not(any(feature = "moka-v08", feature = "moka-v09")))]
-    if config.entry_api {
-        let cache_driver = MokaAsyncCache::with_entry_api(config, max_cap, capacity);
-        return run_multi_tasks(config, num_clients, cache_driver, report_builder).await;
+            if config.entry_api {
+                let cache_driver = MokaAsyncCache::with_entry_api(config, max_cap, init_cap);
+                return run_multi_tasks(config, num_clients, cache_driver, report_builder).await;
+            }
+
+            let cache_driver = MokaAsyncCache::new(config, max_cap, init_cap);
+            run_multi_tasks(config, num_clients, cache_driver, report_builder).await
+        }
+        #[cfg(any(
+            feature = "moka-v08",
+            feature = "moka-v09",
+            feature = "moka-v010",
+            feature = "moka-v011"
+        ))]
+        v if v.is_compiled_in() => {
+            use cache::moka_driver_v1::async_cache::MokaAsyncCache;
+
+            #[cfg(not(any(feature = "moka-v08", feature = "moka-v09")))]
+            if config.entry_api {
+                let cache_driver = MokaAsyncCache::with_entry_api(config, max_cap, init_cap);
+                return run_multi_tasks(config, num_clients, cache_driver, report_builder).await;
+            }
+
+            let cache_driver = MokaAsyncCache::new(config, max_cap, init_cap);
+            run_multi_tasks(config, num_clients, cache_driver, report_builder).await
+        }
+        _ => moka_not_compiled_in(version),
     }
+}
 
-    let cache_driver = MokaAsyncCache::new(config, max_cap, capacity);
-    run_multi_tasks(config, num_clients, cache_driver, report_builder).await
+fn moka_not_compiled_in(version: MokaVersion) -> anyhow::Result<Report> {
+    anyhow::bail!(
+        r#"Moka {version} is not compiled in. Enable the crate feature "{}""#,
+        version.feature()
+    )
 }
 
 #[cfg(any(feature = "mini-moka", feature = "moka-v08", feature = "moka-v09"))]
@@ -156,12 +432,9 @@ pub fn run_multi_threads_moka_dash(
     capacity: usize,
     num_clients: u16,
 ) -> anyhow::Result<Report> {
-    let max_cap = if config.size_aware {
-        capacity as u64 * 2u64.pow(15)
-    } else {
-        capacity as u64
-    };
-    let cache_driver = MiniMokSyncCache::new(config, max_cap, capacity);
+    let max_cap = config.max_capacity(capacity);
+    let init_cap = config.initial_capacity(capacity);
+    let cache_driver = MiniMokSyncCache::new(config, max_cap, init_cap);
     let report_name = if cfg!(feature = "mini-moka") {
         "Mini Moka Sync Cache"
     } else {
@@ -189,12 +462,9 @@ pub fn run_multi_threads_quick_cache(
     capacity: usize,
     num_clients: u16,
 ) -> anyhow::Result<Report> {
-    let max_cap = if config.size_aware {
-        capacity as u64 * 2u64.pow(15)
-    } else {
-        capacity as u64
-    };
-    let cache_driver = QuickCache::new(config, capacity, max_cap);
+    let max_cap = config.max_capacity(capacity);
+    let init_cap = config.initial_capacity(capacity);
+    let cache_driver = QuickCache::new(config, init_cap, max_cap);
     let report_builder =
         ReportBuilder::new("QuickCache Sync Cache", capacity as _, Some(num_clients));
     run_multi_threads(config, num_clients, cache_driver, report_builder)
@@ -222,40 +492,43 @@ pub fn run_multi_threads_tiny_ufo(
     run_multi_threads(config, num_clients, cache_driver, report_builder)
 }
 
+/// Runs a reference policy from a single client.
+pub fn run_reference_policy(
+    config: &Config,
+    capacity: usize,
+    policy: ReferencePolicy,
+) -> anyhow::Result<Report> {
+    let cache_driver = PolicyCache::new(config, capacity, policy);
+    let report_builder = ReportBuilder::new(policy.display_name(), capacity as _, Some(1));
+    run_multi_threads(config, 1, cache_driver, report_builder)
+}
+
 #[cfg(any(feature = "mini-moka", feature = "moka-v08", feature = "moka-v09"))]
 pub fn run_single(config: &Config, capacity: usize) -> anyhow::Result<Report> {
-    let mut max_cap = capacity.try_into().unwrap();
-    if config.size_aware {
-        max_cap *= 2u64.pow(15);
-    }
-    let mut cache_driver = MiniMokaUnsyncCache::new(config, max_cap, capacity);
+    let max_cap = config.max_capacity(capacity);
+    let init_cap = config.initial_capacity(capacity);
+    let mut cache_driver = MiniMokaUnsyncCache::new(config, max_cap, init_cap);
     let name = if cfg!(feature = "mini-moka") {
         "Mini Moka Unsync Cache"
     } else {
         "Moka Unsync Cache"
     };
     let mut report = Report::new(name, max_cap, Some(1));
-    let mut counter = 0;
-
-    // pre-process all commands to reduce benchmark harness influence.
-    let mut all_commands = Vec::new();
-    for _ in 0..(config.repeat.unwrap_or(1)) {
-        let f = File::open(config.trace_file.path())?;
-        let reader = BufReader::new(f);
-
-        for chunk in reader.lines().enumerate().chunks(BATCH_SIZE).into_iter() {
-            let chunk = chunk.map(|(i, r)| r.map(|s| (i, s)));
-            let commands = load_gen::generate_commands(config, BATCH_SIZE, &mut counter, chunk)?;
-            all_commands.push(commands);
-        }
+    if config.latency {
+        report.enable_latencies();
     }
 
+    // pre-process all commands to reduce benchmark harness influence (unless in
+    // the streaming mode).
+    let source = CommandSource::new(config)?;
+
     let instant = Instant::now();
-    for commands in all_commands {
-        cache::process_commands(commands, &mut cache_driver, &mut report);
+    while let Some(commands) = source.next_chunk() {
+        cache::process_commands(&commands, &mut cache_driver, &mut report);
     }
     let elapsed = instant.elapsed();
     report.duration = Some(elapsed);
+    report.streaming = source.finish()?;
 
     Ok(report)
 }
@@ -265,38 +538,31 @@ fn run_multi_threads(
     config: &Config,
     num_clients: u16,
     cache_driver: impl CacheDriver<TraceEntry> + Clone + Send + 'static,
-    report_builder: ReportBuilder,
+    mut report_builder: ReportBuilder,
 ) -> anyhow::Result<Report> {
+    if config.simulate {
+        return simulate(config, cache_driver, report_builder);
+    }
+
+    report_builder.enable_latencies(config.latency);
     let report_builder = Arc::new(report_builder);
-    let (send, receive) = crossbeam_channel::unbounded::<Vec<Command>>();
 
     // In order to have the minimum harness overhead and not have many consumers
-    // waiting for the single producer, we buffer all operations in a channel.
-    let mut counter = 0;
-    for _ in 0..(config.repeat.unwrap_or(1)) {
-        let f = File::open(config.trace_file.path())?;
-        let reader = BufReader::new(f);
-        for chunk in reader.lines().enumerate().chunks(BATCH_SIZE).into_iter() {
-            let chunk = chunk.map(|(i, r)| r.map(|s| (i, s)));
-            let commands = load_gen::generate_commands(config, BATCH_SIZE, &mut counter, chunk)?;
-            send.send(commands)?;
-        }
-    }
-
-    // Drop the sender channel to notify the workers that we are finished.
-    std::mem::drop(send);
+    // waiting for the single producer, we generate all operations beforehand,
+    // unless in the streaming mode.
+    let source = CommandSource::new(config)?;
 
     let instant = Instant::now();
     let handles = (0..num_clients)
         .map(|_| {
             let mut cache = cache_driver.clone();
-            let ch = receive.clone();
+            let source = source.clone();
             let rb = Arc::clone(&report_builder);
 
             std::thread::spawn(move || {
                 let mut report = rb.build();
-                while let Ok(commands) = ch.recv() {
-                    cache::process_commands(commands, &mut cache, &mut report);
+                while let Some(commands) = source.next_chunk() {
+                    cache::process_commands(&commands, &mut cache, &mut report);
                 }
                 report
             })
@@ -314,6 +580,7 @@ fn run_multi_threads(
     let mut report = report_builder.build();
     report.duration = Some(elapsed);
     reports.iter().for_each(|r| report.merge(r));
+    report.streaming = source.finish()?;
 
     if config.is_eviction_listener_enabled() {
         report.add_eviction_counts(cache_driver.eviction_counters().as_ref().unwrap());
@@ -326,39 +593,32 @@ async fn run_multi_tasks(
     config: &Config,
     num_clients: u16,
     cache_driver: impl AsyncCacheDriver<TraceEntry> + Clone + Send + 'static,
-    report_builder: ReportBuilder,
+    mut report_builder: ReportBuilder,
 ) -> anyhow::Result<Report> {
+    if config.simulate {
+        return simulate_async(config, cache_driver, report_builder).await;
+    }
+
+    report_builder.enable_latencies(config.latency);
     let report_builder = Arc::new(report_builder);
-    let (send, receive) = crossbeam_channel::unbounded::<Vec<Command>>();
 
     // In order to have the minimum harness overhead and not have many consumers
-    // waiting for the single producer, we buffer all operations in a channel.
-    let mut counter = 0;
-    for _ in 0..(config.repeat.unwrap_or(1)) {
-        let f = File::open(config.trace_file.path())?;
-        let reader = BufReader::new(f);
-        for chunk in reader.lines().enumerate().chunks(BATCH_SIZE).into_iter() {
-            let chunk = chunk.map(|(i, r)| r.map(|s| (i, s)));
-            let commands = load_gen::generate_commands(config, BATCH_SIZE, &mut counter, chunk)?;
-            send.send(commands)?;
-        }
-    }
-
-    // Drop the sender channel to notify the workers that we are finished.
-    std::mem::drop(send);
+    // waiting for the single producer, we generate all operations beforehand,
+    // unless in the streaming mode.
+    let source = CommandSource::new(config)?;
 
     let instant = Instant::now();
     let handles = (0..num_clients)
         .map(|_| {
             let mut cache = cache_driver.clone();
-            let ch = receive.clone();
+            let source = source.clone();
             let rb = Arc::clone(&report_builder);
             let mut count = 0u32;
 
             rt::spawn(async move {
                 let mut report = rb.build();
-                while let Ok(commands) = ch.recv() {
-                    cache::process_commands_async(commands, &mut cache, &mut report).await;
+                while let Some(commands) = source.next_chunk() {
+                    cache::process_commands_async(&commands, &mut cache, &mut report).await;
                     count += 1;
                     if count % 10_000 == 0 {
                         tokio::task::yield_now().await;
@@ -384,6 +644,100 @@ async fn run_multi_tasks(
         #[cfg(feature = "rt-async-std")]
         report.merge(&r);
     }
+    report.streaming = source.finish()?;
+
+    if config.is_eviction_listener_enabled() {
+        report.add_eviction_counts(cache_driver.eviction_counters().as_ref().unwrap());
+    }
+
+    Ok(report)
+}
+
+/// Drives the cache in the trace order on the current thread, and runs its
+/// pending tasks every `config.pending_tasks_interval` commands. Unlike
+/// `run_multi_threads`, the commands are not buffered, and the results (other
+/// than the duration) are reproducible across runs.
+fn simulate(
+    config: &Config,
+    mut cache_driver: impl CacheDriver<TraceEntry>,
+    report_builder: ReportBuilder,
+) -> anyhow::Result<Report> {
+    let mut report = report_builder.build();
+    let interval = config.pending_tasks_interval;
+    let mut count = 0usize;
+
+    // Time only the commands, not the parsing of the trace between the chunks.
+    let mut elapsed = Duration::ZERO;
+    load_gen::for_each_command_chunk(config, |commands| {
+        let instant = Instant::now();
+        for command in commands {
+            cache::process_command(&command, &mut cache_driver, &mut report);
+            count += 1;
+            if count % interval == 0 {
+                cache_driver.run_pending_tasks();
+            }
+        }
+        elapsed += instant.elapsed();
+        Ok(())
+    })?;
+    let instant = Instant::now();
+    cache_driver.run_pending_tasks();
+    report.duration = Some(elapsed + instant.elapsed());
+
+    if config.is_eviction_listener_enabled() {
+        report.add_eviction_counts(cache_driver.eviction_counters().as_ref().unwrap());
+    }
+
+    Ok(report)
+}
+
+/// The number of command chunks that the producer of `simulate_async` can parse
+/// ahead of the cache.
+const SIMULATE_CHANNEL_CAPACITY: usize = 64;
+
+/// The async version of `simulate`.
+async fn simulate_async(
+    config: &Config,
+    mut cache_driver: impl AsyncCacheDriver<TraceEntry>,
+    report_builder: ReportBuilder,
+) -> anyhow::Result<Report> {
+    let mut report = report_builder.build();
+    let interval = config.pending_tasks_interval;
+    let mut count = 0usize;
+
+    // `for_each_command_chunk` takes a sync closure, so parse the trace on a
+    // producer thread and receive the chunks through a bounded channel. Only a few
+    // chunks are in memory at a time, and the parsing is not timed. The receive
+    // blocks, so it runs on a thread for blocking tasks rather than on the async
+    // runtime's worker.
+    let (send, receive) = crossbeam_channel::bounded(SIMULATE_CHANNEL_CAPACITY);
+    let producer_config = config.clone();
+    let producer = std::thread::spawn(move || {
+        load_gen::for_each_command_chunk(&producer_config, |commands| Ok(send.send(commands)?))
+    });
+
+    let mut elapsed = Duration::ZERO;
+    loop {
+        let receive = receive.clone();
+        let Some(commands) = rt::spawn_blocking(move || receive.recv().ok()).await else {
+            break;
+        };
+        let instant = Instant::now();
+        for command in commands {
+            cache::process_command_async(&command, &mut cache_driver, &mut report).await;
+            count += 1;
+            if count % interval == 0 {
+                cache_driver.run_pending_tasks().await;
+            }
+        }
+        elapsed += instant.elapsed();
+    }
+    let instant = Instant::now();
+    cache_driver.run_pending_tasks().await;
+    report.duration = Some(elapsed + instant.elapsed());
+    rt::spawn_blocking(move || producer.join())
+        .await
+        .expect("The producer panicked")?;
 
     if config.is_eviction_listener_enabled() {
         report.add_eviction_counts(cache_driver.eviction_counters().as_ref().unwrap());
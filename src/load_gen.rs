@@ -1,44 +1,424 @@
+use std::{
+    io::BufRead,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{TryRecvError, TrySendError};
+use itertools::Itertools;
+
 use crate::{
+    binary_trace::{self, BinaryTraceEntries},
     config::Config,
-    parser::{GenericTraceParser, TraceParser},
+    op_mix::{OpMix, OpSelector, Operation},
+    parser::{GenericTraceParser, TraceEntry, TraceParser},
+    report::StreamingStats,
+    trace_file::{TraceFile, TraceFileGroup},
     Command,
 };
 
-pub(crate) fn generate_commands<I>(
+pub(crate) const BATCH_SIZE: usize = 200;
+
+pub(crate) type TraceEntries = Box<dyn Iterator<Item = anyhow::Result<TraceEntry>> + Send>;
+
+/// Returns the entries of the trace file, or the entries generated by the
+/// synthetic workload generator. If the trace file has an up-to-date binary file
+/// written by `mokabench convert` in the same format, the entries are read from
+/// it, unless the weights are read from a column of the trace file, which the
+/// binary file does not have. With `config.weighted_ranges`, each entry is
+/// converted into a single weighted key.
+pub(crate) fn trace_entries(config: &Config) -> anyhow::Result<TraceEntries> {
+    if let TraceFile::Synthetic(workload) = &config.trace_file {
+        return Ok(Box::new(workload.generate().map(Ok)));
+    }
+
+    let path = config.trace_file.path();
+    let entries: TraceEntries = if binary_trace::is_binary_path(&path) {
+        if config.weight_column.is_some() {
+            anyhow::bail!(
+                "The binary trace file {} has no weight column. Use the trace file instead",
+                path.display()
+            );
+        }
+        Box::new(BinaryTraceEntries::open(&path, config.trace_file.format())?)
+    } else if let Some(binary_path) =
+        binary_trace::find_up_to_date(&path, TraceFileGroup::new(&config.trace_file))
+            .filter(|_| config.weight_column.is_none())
+    {
+        Box::new(BinaryTraceEntries::open(&binary_path, None)?)
+    } else {
+        parse_trace_file(&config.trace_file, config.weight_column)?
+    };
+    if config.weighted_ranges {
+        return Ok(Box::new(
+            entries.map(|entry| entry.map(TraceEntry::into_weighted)),
+        ));
+    }
+    Ok(entries)
+}
+
+/// Parses the lines of the trace file, reading the weights from `weight_column` if
+/// given.
+pub(crate) fn parse_trace_file(
+    trace_file: &TraceFile,
+    weight_column: Option<usize>,
+) -> anyhow::Result<TraceEntries> {
+    let reader = trace_file.open()?;
+    let mut parser = GenericTraceParser::new(trace_file.format(), weight_column);
+    let entries = reader
+        .lines()
+        .enumerate()
+        .filter_map(move |(line_number, line)| match line {
+            Ok(line) => parser.parse(&line, line_number).transpose(),
+            Err(e) => Some(Err(e.into())),
+        });
+    Ok(Box::new(entries))
+}
+
+/// Reads the trace `repeat` times, and passes the generated commands to `f` in
+/// chunks of `BATCH_SIZE` entries. With `config.max_ops`, it instead cycles the
+/// trace until that number of commands is generated.
+pub(crate) fn for_each_command_chunk(
+    config: &Config,
+    f: impl FnMut(Vec<Command>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    for_each_command_chunk_up_to(config, config.max_ops, config.max_ops.is_some(), f)
+}
+
+/// Generates the commands until `max_ops` commands. If `cycle` is `true`, the
+/// trace is read again and again until then (or until `f` returns an error).
+/// Otherwise, it is read `repeat` times.
+fn for_each_command_chunk_up_to(
     config: &Config,
-    max_chunk_size: usize,
-    counter: &mut usize,
-    chunk: I,
-) -> anyhow::Result<Vec<Command>>
-where
-    I: Iterator<Item = std::io::Result<(usize, String)>>,
-{
-    let mut parser = GenericTraceParser;
-    let mut ops = Vec::with_capacity(max_chunk_size);
-    for line_result in chunk {
-        let (line_number, line) = line_result?;
-        let Some(entry) = parser.parse(&line, line_number)? else {
-            continue;
-        };
-        *counter += 1;
-        if config.invalidate_all && *counter % 100_000 == 0 {
+    max_ops: Option<u64>,
+    cycle: bool,
+    mut f: impl FnMut(Vec<Command>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut generator = CommandGenerator::new(config);
+    let mut remaining = max_ops.unwrap_or(u64::MAX);
+    let mut pass = 0;
+    while remaining > 0 {
+        if !cycle && pass >= config.repeat.unwrap_or(1) {
+            break;
+        }
+        pass += 1;
+
+        let mut is_empty = true;
+        let entries = trace_entries(config)?;
+        for chunk in entries.chunks(BATCH_SIZE).into_iter() {
+            let mut commands = generator.generate_commands(BATCH_SIZE, chunk)?;
+            is_empty &= commands.is_empty();
+            if commands.len() as u64 >= remaining {
+                commands.truncate(remaining as usize);
+                remaining = 0;
+                f(commands)?;
+                break;
+            }
+            remaining -= commands.len() as u64;
+            f(commands)?;
+        }
+        if is_empty {
+            // Do not cycle an empty trace forever.
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Where the workers take the command chunks from.
+#[derive(Clone)]
+pub(crate) enum CommandSource {
+    /// The chunks buffered in a channel. Each chunk is run once.
+    Channel(crossbeam_channel::Receiver<Vec<Command>>),
+    /// The chunks cycled over until the time limit (`--duration`).
+    Cycle(Arc<CommandCycle>),
+    /// The chunks generated by a producer thread while the workers run
+    /// (`--streaming`).
+    Stream(Arc<CommandStream>),
+}
+
+impl CommandSource {
+    /// Generates all commands of the run before the workers start, so that the
+    /// harness overhead is minimum.
+    pub(crate) fn new(config: &Config) -> anyhow::Result<Self> {
+        if config.streaming {
+            return Ok(Self::Stream(Arc::new(CommandStream::start(config))));
+        }
+
+        if let Some(time_limit) = config.time_limit {
+            // Generate the trace once, and let `CommandCycle` apply `max_ops`.
+            let mut chunks = Vec::new();
+            for_each_command_chunk_up_to(config, None, false, |commands| {
+                chunks.push(commands);
+                Ok(())
+            })?;
+            return Ok(Self::Cycle(Arc::new(CommandCycle::new(
+                chunks,
+                time_limit,
+                config.max_ops,
+            ))));
+        }
+
+        let (send, receive) = crossbeam_channel::unbounded::<Vec<Command>>();
+        for_each_command_chunk(config, |commands| Ok(send.send(commands)?))?;
+        // Drop the sender channel to notify the workers that we are finished.
+        std::mem::drop(send);
+        Ok(Self::Channel(receive))
+    }
+
+    /// Returns the next chunk, or `None` if the run is finished.
+    pub(crate) fn next_chunk(&self) -> Option<CommandChunk> {
+        match self {
+            Self::Channel(ch) => ch.recv().ok().map(CommandChunk::Owned),
+            Self::Cycle(cycle) => cycle.next_chunk(),
+            Self::Stream(stream) => stream.next_chunk().map(CommandChunk::Owned),
+        }
+    }
+
+    /// Waits for the producer thread to finish, and returns the streaming stats.
+    /// It must be called after all workers have finished.
+    pub(crate) fn finish(self) -> anyhow::Result<Option<StreamingStats>> {
+        match self {
+            Self::Stream(stream) => {
+                let stream = Arc::into_inner(stream).expect("The workers must have finished");
+                stream.finish().map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// A chunk of commands taken from a `CommandSource`.
+pub(crate) enum CommandChunk {
+    Owned(Vec<Command>),
+    /// The first `len` commands of a chunk of a `CommandCycle`. It is shared with
+    /// the other workers, so that taking it does not copy the commands.
+    Shared(Arc<[Command]>, usize),
+}
+
+impl Deref for CommandChunk {
+    type Target = [Command];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Owned(commands) => commands,
+            Self::Shared(commands, len) => &commands[..*len],
+        }
+    }
+}
+
+/// The command chunks shared by the workers, which take them in turn from the
+/// beginning again after the last one, until the deadline.
+pub(crate) struct CommandCycle {
+    chunks: Vec<Arc<[Command]>>,
+    next: AtomicUsize,
+    deadline: Instant,
+    max_ops: Option<u64>,
+    taken_ops: AtomicU64,
+}
+
+impl CommandCycle {
+    /// The deadline is `time_limit` from now.
+    fn new(chunks: Vec<Vec<Command>>, time_limit: Duration, max_ops: Option<u64>) -> Self {
+        Self {
+            chunks: chunks.into_iter().map(Arc::from).collect(),
+            next: AtomicUsize::default(),
+            deadline: Instant::now() + time_limit,
+            max_ops,
+            taken_ops: AtomicU64::default(),
+        }
+    }
+
+    fn next_chunk(&self) -> Option<CommandChunk> {
+        if self.chunks.is_empty() || Instant::now() >= self.deadline {
+            return None;
+        }
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.chunks.len();
+        let commands = Arc::clone(&self.chunks[i]);
+        let mut len = commands.len();
+
+        if let Some(max_ops) = self.max_ops {
+            let taken = self.taken_ops.fetch_add(len as u64, Ordering::Relaxed);
+            if taken >= max_ops {
+                return None;
+            }
+            len = (max_ops - taken).min(len as u64) as usize;
+        }
+        Some(CommandChunk::Shared(commands, len))
+    }
+}
+
+/// Converts trace entries into cache commands. It keeps the state across chunks
+/// so that the operations are spread over the whole trace.
+pub(crate) struct CommandGenerator<'a> {
+    config: &'a Config,
+    counter: usize,
+    op_selector: Option<OpSelector>,
+}
+
+impl<'a> CommandGenerator<'a> {
+    pub(crate) fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            counter: 0,
+            op_selector: config.op_mix.as_ref().map(OpMix::selector),
+        }
+    }
+
+    pub(crate) fn generate_commands<I>(
+        &mut self,
+        max_chunk_size: usize,
+        chunk: I,
+    ) -> anyhow::Result<Vec<Command>>
+    where
+        I: Iterator<Item = anyhow::Result<TraceEntry>>,
+    {
+        let mut ops = Vec::with_capacity(max_chunk_size);
+        for entry_result in chunk {
+            let entry = entry_result?;
+            self.counter += 1;
+            if let Some(selector) = &mut self.op_selector {
+                Self::push_mixed_command(&mut ops, selector.next_op(), entry);
+            } else {
+                self.push_command(&mut ops, entry);
+            }
+        }
+        Ok(ops)
+    }
+
+    fn push_command(&self, ops: &mut Vec<Command>, entry: TraceEntry) {
+        let config = self.config;
+        let counter = self.counter;
+        if config.invalidate_all && counter % 100_000 == 0 {
             ops.push(Command::InvalidateAll);
             ops.push(Command::GetOrInsert(entry));
-        } else if config.invalidate_entries_if && *counter % 5_000 == 0 {
+        } else if config.invalidate_entries_if && counter % 5_000 == 0 {
             ops.push(Command::InvalidateEntriesIf(entry));
-        } else if config.size_aware && *counter % 11 == 0 {
+        } else if config.size_aware && counter % 11 == 0 {
             ops.push(Command::Update(entry));
-        } else if config.invalidate && *counter % 8 == 0 {
+        } else if config.invalidate && counter % 8 == 0 {
             ops.push(Command::Invalidate(entry));
-        } else if config.insert_once && *counter % 3 == 0 {
+        } else if config.insert_once && counter % 3 == 0 {
             ops.push(Command::GetOrInsertOnce(entry));
         } else {
             ops.push(Command::GetOrInsert(entry));
         }
 
-        if config.iterate && *counter % 50_000 == 0 {
+        if config.iterate && counter % 50_000 == 0 {
             ops.push(Command::Iterate);
         }
     }
-    Ok(ops)
+
+    // `InvalidateAll` and `Iterate` do not take a key, so they are followed by a
+    // `GetOrInsert` of the entry to keep replaying the trace.
+    fn push_mixed_command(ops: &mut Vec<Command>, op: Operation, entry: TraceEntry) {
+        match op {
+            Operation::Get => ops.push(Command::GetOrInsert(entry)),
+            Operation::GetWith => ops.push(Command::GetOrInsertOnce(entry)),
+            Operation::Update => ops.push(Command::Update(entry)),
+            Operation::Invalidate => ops.push(Command::Invalidate(entry)),
+            Operation::InvalidateEntriesIf => ops.push(Command::InvalidateEntriesIf(entry)),
+            Operation::InvalidateAll => {
+                ops.push(Command::InvalidateAll);
+                ops.push(Command::GetOrInsert(entry));
+            }
+            Operation::Iterate => {
+                ops.push(Command::Iterate);
+                ops.push(Command::GetOrInsert(entry));
+            }
+        }
+    }
+}
+
+/// The commands sent from a producer thread, which reads the trace while the
+/// workers run, through a bounded channel. Only `channel_capacity` chunks are in
+/// memory at a time.
+pub(crate) struct CommandStream {
+    receiver: crossbeam_channel::Receiver<Vec<Command>>,
+    producer: std::thread::JoinHandle<anyhow::Result<Duration>>,
+    deadline: Option<Instant>,
+    channel_capacity: usize,
+    // The total time the workers waited for the producer on an empty channel.
+    wait_nanos: AtomicU64,
+}
+
+impl CommandStream {
+    fn start(config: &Config) -> Self {
+        let (send, receive) = crossbeam_channel::bounded(config.channel_capacity);
+        let producer_config = config.clone();
+        let producer = std::thread::spawn(move || {
+            let config = producer_config;
+            // The total time the producer was blocked on a full channel.
+            let mut stall = Duration::ZERO;
+            let result = for_each_command_chunk_up_to(
+                &config,
+                config.max_ops,
+                config.max_ops.is_some() || config.time_limit.is_some(),
+                |commands| match send.try_send(commands) {
+                    Ok(()) => Ok(()),
+                    Err(TrySendError::Full(commands)) => {
+                        let start = Instant::now();
+                        let result = send.send(commands);
+                        stall += start.elapsed();
+                        Ok(result.map_err(|_| Disconnected)?)
+                    }
+                    Err(TrySendError::Disconnected(_)) => Err(Disconnected.into()),
+                },
+            );
+            match result {
+                // The workers stopped at the time limit.
+                Err(e) if e.is::<Disconnected>() => Ok(stall),
+                Err(e) => Err(e),
+                Ok(()) => Ok(stall),
+            }
+        });
+
+        Self {
+            receiver: receive,
+            producer,
+            deadline: config.time_limit.map(|limit| Instant::now() + limit),
+            channel_capacity: config.channel_capacity,
+            wait_nanos: AtomicU64::default(),
+        }
+    }
+
+    fn next_chunk(&self) -> Option<Vec<Command>> {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return None;
+        }
+        match self.receiver.try_recv() {
+            Ok(commands) => Some(commands),
+            Err(TryRecvError::Disconnected) => None,
+            Err(TryRecvError::Empty) => {
+                let start = Instant::now();
+                let commands = self.receiver.recv().ok();
+                self.wait_nanos
+                    .fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+                commands
+            }
+        }
+    }
+
+    fn finish(self) -> anyhow::Result<StreamingStats> {
+        // Disconnect the channel so that the producer stops if it is still
+        // running (e.g. after the time limit).
+        std::mem::drop(self.receiver);
+        let producer_stall = self.producer.join().expect("The producer panicked")?;
+        Ok(StreamingStats {
+            channel_capacity: self.channel_capacity,
+            producer_stall,
+            client_wait: Duration::from_nanos(self.wait_nanos.into_inner()),
+        })
+    }
 }
+
+#[derive(Debug, thiserror::Error)]
+#[error("The channel is disconnected")]
+struct Disconnected;
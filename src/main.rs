@@ -1,11 +1,28 @@
+use std::io::Write;
+
 use anyhow::Context;
 use mokabench::{
     self,
-    config::{Config, RemovalNotificationMode},
-    Report, TraceFile,
+    analyze::TraceAnalysis,
+    binary_trace,
+    compare::{self, Comparison, Thresholds},
+    config::{
+        CacheKind, Config, MokaVersion, ReferencePolicy, RemovalNotificationMode, ValueKind,
+        DEFAULT_CHANNEL_CAPACITY, DEFAULT_MAX_CV, DEFAULT_PENDING_TASKS_INTERVAL,
+        DEFAULT_VALUE_SIZE,
+    },
+    generator::{self, Workload},
+    mrc::{self, MissRatioCurve, MrcMethod},
+    op_mix::{MixMode, OpMix},
+    opt::Belady,
+    output::{OutputFormat, ReportWriter},
+    scenario::ScenarioFile,
+    trace_file::{TraceFileGroup, DATASET_DIR_ENV},
+    weight::{self, WeightDist},
+    Capabilities, TraceFile,
 };
 
-use clap::{Arg, Command};
+use clap::{Arg, ArgMatches, Command};
 
 #[cfg(feature = "rt-tokio")]
 #[tokio::main]
@@ -20,32 +37,251 @@ async fn main() -> anyhow::Result<()> {
 }
 
 async fn run(async_rt_name: &str) -> anyhow::Result<()> {
-    let (trace_files, mut config) = create_config()?;
+    let matches = build_app().get_matches();
+    if let Some(matches) = matches.subcommand_matches(SUBCOMMAND_ANALYZE) {
+        return analyze(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches(SUBCOMMAND_COMPARE) {
+        return compare(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches(SUBCOMMAND_CONVERT) {
+        return convert(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches(SUBCOMMAND_LIST) {
+        if matches.subcommand_matches(SUBCOMMAND_CACHES).is_some() {
+            list_caches()?;
+        }
+        return Ok(());
+    }
+
+    let (trace_files, config, mut writer) = create_config(&matches)?;
 
-    println!("Async runtime: {async_rt_name}");
+    writer.info(&format!("Async runtime: {async_rt_name}"));
+
+    // Each scenario in the file replaces some of the options given by the command
+    // line.
+    let runs = match matches.value_of(OPTION_SCENARIO) {
+        None => vec![(trace_files, config)],
+        Some(path) => ScenarioFile::load(std::path::Path::new(path))?
+            .scenarios
+            .iter()
+            .map(|scenario| scenario.to_config(&config))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
 
+    for (trace_files, mut config) in runs {
+        if let Some(label) = &config.label {
+            writer.info(&format!("Scenario: {label}"));
+        }
+        skip_unsupported_caches(&mut config, &writer);
+        run_traces(trace_files, config, &mut writer).await?;
+    }
+
+    Ok(())
+}
+
+async fn run_traces(
+    trace_files: Vec<TraceFile>,
+    mut config: Config,
+    writer: &mut ReportWriter,
+) -> anyhow::Result<()> {
     for trace_file in trace_files {
         config.trace_file = trace_file;
-        println!("{config:?}");
-        println!();
 
+        if let Some(method) = config.mrc {
+            let mrc = MissRatioCurve::compute(&config, method)?;
+            let mut plot = Vec::new();
+            mrc.write_plot(&mut plot)?;
+            writer.info(&String::from_utf8(plot)?);
+
+            // Run the caches only at the capacities selected on the curve.
+            let Some(points) = config.mrc_points else {
+                continue;
+            };
+            config.set_capacities(Some(mrc.select_capacities(points)));
+        }
+
+        writer.begin_trace(&config)?;
+
+        // Read the whole trace only once for all capacities.
+        let belady = if config.opt {
+            Some(Belady::new(&config)?)
+        } else {
+            None
+        };
+
+        for capacity in config.capacities() {
+            if let Some(belady) = &belady {
+                writer.write_report(&config, &belady.run(config.max_capacity(*capacity)))?;
+            }
+            run_with_capacity(&config, *capacity, writer).await?
+        }
+
+        writer.end_trace()?;
+    }
+
+    Ok(())
+}
+
+fn analyze(matches: &ArgMatches) -> anyhow::Result<()> {
+    let mut out = std::io::stdout().lock();
+
+    for (i, trace_file) in parse_trace_files(matches)?.into_iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        let config = Config::new(trace_file, None, None, None, None, None);
+        let analysis = TraceAnalysis::analyze(&config)?;
+        analysis.write_report(&mut out)?;
+
+        let defaults = config.trace_file.default_capacities();
+        if !defaults.is_empty() {
+            let list = defaults.iter().map(ToString::to_string).collect::<Vec<_>>();
+            writeln!(
+                out,
+                "  (The current default capacities: {})",
+                list.join(", ")
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn compare(matches: &ArgMatches) -> anyhow::Result<()> {
+    let parse_threshold =
+        |name: &str, default: f64| -> anyhow::Result<f64> {
+            match matches.value_of(name) {
+                None => Ok(default),
+                Some(v) => v.parse().ok().filter(|t: &f64| *t >= 0.0).with_context(|| {
+                    format!(r#"Cannot parse {name} "{v}" as a non-negative number"#)
+                }),
+            }
+        };
+    let thresholds = Thresholds {
+        throughput: parse_threshold(
+            OPTION_THROUGHPUT_THRESHOLD,
+            compare::DEFAULT_THROUGHPUT_THRESHOLD,
+        )?,
+        hit_ratio: parse_threshold(
+            OPTION_HIT_RATIO_THRESHOLD,
+            compare::DEFAULT_HIT_RATIO_THRESHOLD,
+        )?,
+    };
+
+    let old = compare::read_records(std::path::Path::new(matches.value_of(ARG_OLD).unwrap()))?;
+    let new = compare::read_records(std::path::Path::new(matches.value_of(ARG_NEW).unwrap()))?;
+    let comparison = Comparison::new(&old, &new, thresholds);
+    comparison.write_report(&mut std::io::stdout().lock())?;
+
+    if comparison.runs.is_empty() {
+        anyhow::bail!("No runs of the old file matched the runs of the new file");
+    }
+    let missing = comparison.only_in_old.len();
+    if missing > 0 {
+        anyhow::bail!("{missing} runs of the old file are missing in the new file");
+    }
+    let regressions = comparison.regressions().count();
+    if regressions > 0 {
+        anyhow::bail!("{regressions} runs regressed beyond the thresholds");
+    }
+    Ok(())
+}
+
+/// Removes the caches and the reference policies that do not support the options
+/// in the config, and prints the reasons.
+fn skip_unsupported_caches(config: &mut Config, writer: &ReportWriter) {
+    let skip = |name: &str, capabilities: Capabilities, config: &Config| {
+        let unsupported = capabilities.unsupported_options(config);
+        if unsupported.is_empty() {
+            return false;
+        }
+        let options = unsupported
+            .iter()
+            .map(|o| format!("--{o}"))
+            .collect::<Vec<_>>();
+        writer.info(&format!(
+            "Skipping {name}: it does not support {}",
+            options.join(", ")
+        ));
+        true
+    };
+
+    let caches = CacheKind::ALL
+        .into_iter()
+        .filter(|cache| config.is_cache_enabled(*cache))
+        .filter(|cache| match mokabench::cache_capabilities(*cache) {
+            None => false,
+            Some(capabilities) => !skip(cache.name(), capabilities, config),
+        })
+        .collect();
+
+    let policies = config
+        .policies
+        .iter()
+        .copied()
+        .filter(|policy| {
+            let capabilities = mokabench::reference_policy_capabilities();
+            !skip(policy.display_name(), capabilities, config)
+        })
+        .collect();
+
+    config.set_caches(Some(caches));
+    config.set_policies(policies);
+}
+
+fn convert(matches: &ArgMatches) -> anyhow::Result<()> {
+    for trace_file in parse_trace_files(matches)? {
+        let (path, count) = binary_trace::convert(&trace_file)?;
         println!(
-            "{}",
-            Report::cvs_header(config.is_eviction_listener_enabled())
+            "Converted {trace_file}: {count} entries to {}",
+            path.display()
         );
+    }
+    Ok(())
+}
 
-        for capacity in config.trace_file.default_capacities() {
-            run_with_capacity(&config, *capacity).await?
-        }
+fn list_caches() -> anyhow::Result<()> {
+    let mut out = std::io::stdout().lock();
+
+    writeln!(
+        out,
+        "{:<20}  {:<18}  Supported options",
+        "Cache", "Compiled in"
+    )?;
+    for cache in CacheKind::ALL {
+        let compiled_in = if cache.is_compiled_in() {
+            "yes".to_string()
+        } else {
+            format!("no ({})", cache.feature())
+        };
+        let options = match mokabench::cache_capabilities(cache) {
+            None => "-".to_string(),
+            Some(capabilities) if capabilities == Capabilities::NONE => "(none)".to_string(),
+            Some(capabilities) => capabilities.supported_options().join(", "),
+        };
+        writeln!(
+            out,
+            "{:<20}  {:<18}  {}",
+            cache.name(),
+            compiled_in,
+            options
+        )?;
     }
 
     Ok(())
 }
 
-async fn run_with_capacity(config: &Config, capacity: usize) -> anyhow::Result<()> {
+async fn run_with_capacity(
+    config: &Config,
+    capacity: usize,
+    writer: &mut ReportWriter,
+) -> anyhow::Result<()> {
     const DEFAULT_NUM_CLIENTS_ARRAY: &[u16] = &[16, 24, 32, 40, 48];
 
-    let num_clients_slice: &[u16] = if let Some(n) = &config.num_clients {
+    let num_clients_slice: &[u16] = if config.simulate {
+        &[1]
+    } else if let Some(n) = &config.num_clients {
         n
     } else {
         DEFAULT_NUM_CLIENTS_ARRAY
@@ -55,97 +291,136 @@ async fn run_with_capacity(config: &Config, capacity: usize) -> anyhow::Result<(
     // as it doesn't use the producer/consumer thread pattern as the other caches.
 
     #[cfg(any(feature = "mini-moka", feature = "moka-v08", feature = "moka-v09"))]
-    if !config.insert_once && !config.is_eviction_listener_enabled() {
-        let report = mokabench::run_single(config, capacity)?;
-        println!("{}", report.to_csv_record());
+    if config.is_cache_enabled(CacheKind::MokaUnsync) {
+        let report = mokabench::run_iterations(config, || mokabench::run_single(config, capacity))?;
+        writer.write_report(config, &report)?;
     }
 
     #[cfg(feature = "hashlink")]
-    if !config.insert_once
-        && !config.size_aware
-        && !config.invalidate_entries_if
-        && !config.is_eviction_listener_enabled()
-    {
+    if config.is_cache_enabled(CacheKind::HashLink) {
         for num_clients in num_clients_slice {
-            let report = mokabench::run_multi_threads_hashlink(config, capacity, *num_clients)?;
-            println!("{}", report.to_csv_record());
+            let report = mokabench::run_iterations(config, || {
+                mokabench::run_multi_threads_hashlink(config, capacity, *num_clients)
+            })?;
+            writer.write_report(config, &report)?;
         }
     }
 
     #[cfg(feature = "quick_cache")]
-    if !config.insert_once
-        && !config.size_aware
-        && !config.invalidate_entries_if
-        && !config.is_eviction_listener_enabled()
-    {
+    if config.is_cache_enabled(CacheKind::QuickCache) {
         for num_clients in num_clients_slice {
-            let report = mokabench::run_multi_threads_quick_cache(config, capacity, *num_clients)?;
-            println!("{}", report.to_csv_record());
+            let report = mokabench::run_iterations(config, || {
+                mokabench::run_multi_threads_quick_cache(config, capacity, *num_clients)
+            })?;
+            writer.write_report(config, &report)?;
         }
     }
 
     #[cfg(feature = "stretto")]
-    if !config.insert_once
-        && !config.size_aware
-        && !config.invalidate_entries_if
-        && !config.is_eviction_listener_enabled()
-    {
+    if config.is_cache_enabled(CacheKind::Stretto) {
         for num_clients in num_clients_slice {
-            let report = mokabench::run_multi_threads_stretto(config, capacity, *num_clients)?;
-            println!("{}", report.to_csv_record());
+            let report = mokabench::run_iterations(config, || {
+                mokabench::run_multi_threads_stretto(config, capacity, *num_clients)
+            })?;
+            writer.write_report(config, &report)?;
         }
     }
 
     #[cfg(feature = "tiny-ufo")]
-    if !config.insert_once
-        && !config.size_aware
-        && !config.invalidate_entries_if
-        && !config.is_eviction_listener_enabled()
-    {
+    if config.is_cache_enabled(CacheKind::TinyUfo) {
         for num_clients in num_clients_slice {
-            let report = mokabench::run_multi_threads_tiny_ufo(config, capacity, *num_clients)?;
-            println!("{}", report.to_csv_record());
+            let report = mokabench::run_iterations(config, || {
+                mokabench::run_multi_threads_tiny_ufo(config, capacity, *num_clients)
+            })?;
+            writer.write_report(config, &report)?;
         }
     }
 
     #[cfg(any(feature = "mini-moka", feature = "moka-v08", feature = "moka-v09"))]
-    if !config.insert_once
-        && !config.invalidate_entries_if
-        && !config.is_eviction_listener_enabled()
-    {
+    if config.is_cache_enabled(CacheKind::MokaDash) {
         for num_clients in num_clients_slice {
-            let report = mokabench::run_multi_threads_moka_dash(config, capacity, *num_clients)?;
-            println!("{}", report.to_csv_record());
+            let report = mokabench::run_iterations(config, || {
+                mokabench::run_multi_threads_moka_dash(config, capacity, *num_clients)
+            })?;
+            writer.write_report(config, &report)?;
         }
     }
 
-    for num_clients in num_clients_slice {
-        let report = mokabench::run_multi_threads_moka_sync(config, capacity, *num_clients)?;
-        println!("{}", report.to_csv_record());
+    for version in MokaVersion::compiled_in() {
+        if !config.is_cache_enabled(CacheKind::MokaSync(version)) {
+            continue;
+        }
+        for num_clients in num_clients_slice {
+            let report = mokabench::run_iterations(config, || {
+                mokabench::run_multi_threads_moka_sync(config, version, capacity, *num_clients)
+            })?;
+            writer.write_report(config, &report)?;
+        }
     }
 
-    for num_clients in num_clients_slice {
-        let report = mokabench::run_multi_tasks_moka_async(config, capacity, *num_clients).await?;
-        println!("{}", report.to_csv_record());
+    for version in MokaVersion::compiled_in() {
+        if !config.is_cache_enabled(CacheKind::MokaAsync(version)) {
+            continue;
+        }
+        for num_clients in num_clients_slice {
+            let report = mokabench::run_iterations_async(config, || {
+                mokabench::run_multi_tasks_moka_async(config, version, capacity, *num_clients)
+            })
+            .await?;
+            writer.write_report(config, &report)?;
+        }
     }
 
-    let num_segments = 8;
+    for version in MokaVersion::compiled_in() {
+        if !config.is_cache_enabled(CacheKind::MokaSegmented(version)) {
+            continue;
+        }
+        for num_clients in num_clients_slice {
+            let report = mokabench::run_iterations(config, || {
+                mokabench::run_multi_threads_moka_segment(
+                    config,
+                    version,
+                    capacity,
+                    *num_clients,
+                    config.num_segments,
+                )
+            })?;
+            writer.write_report(config, &report)?;
+        }
+    }
 
-    for num_clients in num_clients_slice {
-        let report = mokabench::run_multi_threads_moka_segment(
-            config,
-            capacity,
-            *num_clients,
-            num_segments,
-        )?;
-        println!("{}", report.to_csv_record());
+    for policy in &config.policies {
+        let report = mokabench::run_iterations(config, || {
+            mokabench::run_reference_policy(config, capacity, *policy)
+        })?;
+        writer.write_report(config, &report)?;
     }
 
     Ok(())
 }
 
+const SUBCOMMAND_ANALYZE: &str = "analyze";
+const SUBCOMMAND_COMPARE: &str = "compare";
+const SUBCOMMAND_CONVERT: &str = "convert";
+const SUBCOMMAND_LIST: &str = "list";
+const SUBCOMMAND_CACHES: &str = "caches";
+
+const ARG_OLD: &str = "OLD";
+const ARG_NEW: &str = "NEW";
+const OPTION_THROUGHPUT_THRESHOLD: &str = "throughput-threshold";
+const OPTION_HIT_RATIO_THRESHOLD: &str = "hit-ratio-threshold";
+
 const OPTION_TRACE_FILE: &str = "trace-file";
 const OPTION_TRACE_FILES: &str = "trace-files";
+const OPTION_TRACE_FORMAT: &str = "trace-format";
+const OPTION_CAPACITIES: &str = "capacities";
+const OPTION_GENERATOR: &str = "generator";
+const OPTION_SKEW: &str = "skew";
+const OPTION_HOT_KEY_FRACTION: &str = "hot-key-fraction";
+const OPTION_HOT_OP_FRACTION: &str = "hot-op-fraction";
+const OPTION_KEY_SPACE: &str = "key-space";
+const OPTION_NUM_OPS: &str = "num-ops";
+const OPTION_SEED: &str = "seed";
 const OPTION_TTL: &str = "ttl";
 const OPTION_TTI: &str = "tti";
 const OPTION_NUM_CLIENTS: &str = "num-clients";
@@ -155,8 +430,34 @@ const OPTION_INVALIDATE: &str = "invalidate";
 const OPTION_INVALIDATE_ALL: &str = "invalidate-all";
 const OPTION_INVALIDATE_IF: &str = "invalidate-entries-if";
 const OPTION_ITERATE: &str = "iterate";
+const OPTION_MIX: &str = "mix";
+const OPTION_MIX_MODE: &str = "mix-mode";
 const OPTION_SIZE_AWARE: &str = "size-aware";
+const OPTION_WEIGHTED_RANGES: &str = "weighted-ranges";
+const OPTION_WEIGHT_DIST: &str = "weight-dist";
+const OPTION_WEIGHT_COLUMN: &str = "weight-column";
+const OPTION_VALUE_KIND: &str = "value-kind";
+const OPTION_VALUE_SIZE: &str = "value-size";
+const OPTION_LATENCY: &str = "latency";
+const OPTION_SIMULATE: &str = "simulate";
+const OPTION_PENDING_TASKS_INTERVAL: &str = "pending-tasks-interval";
+const OPTION_OPT: &str = "opt";
+const OPTION_CACHES: &str = "caches";
+const OPTION_POLICIES: &str = "policies";
+const OPTION_MRC: &str = "mrc";
+const OPTION_MRC_SAMPLE_RATE: &str = "mrc-sample-rate";
+const OPTION_MRC_POINTS: &str = "mrc-points";
+const OPTION_SCENARIO: &str = "scenario";
+const OPTION_OUTPUT_FORMAT: &str = "output-format";
+const OPTION_OUTPUT: &str = "output";
 const OPTION_REPEAT: &str = "repeat";
+const OPTION_DURATION: &str = "duration";
+const OPTION_MAX_OPS: &str = "max-ops";
+const OPTION_STREAMING: &str = "streaming";
+const OPTION_CHANNEL_CAPACITY: &str = "channel-capacity";
+const OPTION_ITERATIONS: &str = "iterations";
+const OPTION_WARMUP: &str = "warmup";
+const OPTION_MAX_CV: &str = "max-cv";
 
 // Since Moka v0.9.0
 const OPTION_EVICTION_LISTENER: &str = "eviction-listener";
@@ -167,16 +468,18 @@ const OPTION_ENTRY_API: &str = "entry-api";
 // Since Moka v0.11.0
 const OPTION_PER_KEY_EXPIRATION: &str = "per-key-expiration";
 
-fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
+fn build_app() -> Command<'static> {
     let mut app = Command::new("Moka Bench")
+        .args(trace_args())
         .arg(
-            Arg::new(OPTION_TRACE_FILE)
-                .alias(OPTION_TRACE_FILES)
-                .short('f')
-                .long(OPTION_TRACE_FILE)
-                .help("The trace file (e.g. s3, ds1, oltp). default: s3")
-                .default_value("s3")
-                .default_missing_value("s3")
+            Arg::new(OPTION_CAPACITIES)
+                .short('c')
+                .long(OPTION_CAPACITIES)
+                .help(
+                    "The max capacities of the caches in number of entries, or in bytes \
+                    with a unit (e.g. 64MiB, 1GB), which implies --size-aware. Required \
+                    for custom trace files. default: the trace file's default capacities",
+                )
                 .takes_value(true)
                 .multiple_values(true)
                 .use_value_delimiter(true),
@@ -207,6 +510,70 @@ fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
                 .long(OPTION_REPEAT)
                 .takes_value(true),
         )
+        .arg(
+            Arg::new(OPTION_DURATION)
+                .long(OPTION_DURATION)
+                .help(
+                    "Stop each run after this time, e.g. 30s or 2m, cycling the trace \
+                    as needed",
+                )
+                .takes_value(true)
+                .conflicts_with_all(&[OPTION_REPEAT, OPTION_SIMULATE]),
+        )
+        .arg(
+            Arg::new(OPTION_MAX_OPS)
+                .long(OPTION_MAX_OPS)
+                .help(
+                    "Stop each run after N commands (one per trace entry, plus the \
+                    ones added by the operation mix), cycling the trace as needed",
+                )
+                .takes_value(true)
+                .conflicts_with(OPTION_REPEAT),
+        )
+        .arg(
+            Arg::new(OPTION_STREAMING)
+                .long(OPTION_STREAMING)
+                .help(
+                    "Read the trace in a producer thread while the clients run, instead \
+                    of buffering the whole trace in memory. For huge trace files",
+                )
+                .conflicts_with(OPTION_SIMULATE),
+        )
+        .arg(
+            Arg::new(OPTION_CHANNEL_CAPACITY)
+                .long(OPTION_CHANNEL_CAPACITY)
+                .help(
+                    "In the streaming mode, the max number of chunks of commands in the \
+                    channel between the producer and the clients. default: 64",
+                )
+                .takes_value(true)
+                .requires(OPTION_STREAMING),
+        )
+        .arg(
+            Arg::new(OPTION_ITERATIONS)
+                .long(OPTION_ITERATIONS)
+                .help(
+                    "Run each benchmark N times and report the mean, median, standard \
+                    deviation, min/max and 95% confidence interval. default: 1",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(OPTION_WARMUP)
+                .long(OPTION_WARMUP)
+                .help("Run each benchmark N times before the iterations without reporting. default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(OPTION_MAX_CV)
+                .long(OPTION_MAX_CV)
+                .help(
+                    "Flag the runs whose coefficient of variation of the duration or \
+                    throughput exceeds this percent. default: 5",
+                )
+                .takes_value(true)
+                .requires(OPTION_ITERATIONS),
+        )
         .arg(
             Arg::new(OPTION_INSERTION_DELAY)
                 .short('d')
@@ -218,9 +585,185 @@ fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
         .arg(Arg::new(OPTION_INVALIDATE_ALL).long(OPTION_INVALIDATE_ALL))
         .arg(Arg::new(OPTION_INVALIDATE_IF).long(OPTION_INVALIDATE_IF))
         .arg(Arg::new(OPTION_ITERATE).long(OPTION_ITERATE))
-        .arg(Arg::new(OPTION_SIZE_AWARE).long(OPTION_SIZE_AWARE));
+        .arg(
+            Arg::new(OPTION_MIX)
+                .long(OPTION_MIX)
+                .help(
+                    "The weights of the operations (get, get-with, update, invalidate, \
+                    invalidate-all, invalidate-if, iterate). e.g. get=80,update=10,invalidate=10",
+                )
+                .takes_value(true)
+                .multiple_values(true)
+                .use_value_delimiter(true)
+                .conflicts_with_all(&[
+                    OPTION_INSERT_ONCE,
+                    OPTION_INVALIDATE,
+                    OPTION_INVALIDATE_ALL,
+                    OPTION_INVALIDATE_IF,
+                    OPTION_ITERATE,
+                ]),
+        )
+        .arg(
+            Arg::new(OPTION_MIX_MODE)
+                .long(OPTION_MIX_MODE)
+                .help(
+                    "How to choose the operations in the mix: \"periodic\" (deterministic) \
+                    or \"random\" (seeded by --seed). default: periodic",
+                )
+                .takes_value(true)
+                .requires(OPTION_MIX),
+        )
+        .arg(Arg::new(OPTION_SIZE_AWARE).long(OPTION_SIZE_AWARE))
+        .arg(
+            Arg::new(OPTION_WEIGHTED_RANGES)
+                .long(OPTION_WEIGHTED_RANGES)
+                .help(
+                    "Replay each range of the ARC traces as a single key weighted by the \
+                    length of the range, instead of a key per block. Implies --size-aware",
+                ),
+        )
+        .arg(
+            Arg::new(OPTION_WEIGHT_DIST)
+                .long(OPTION_WEIGHT_DIST)
+                .help(
+                    "Draw the weights of the values in bytes from a distribution seeded by \
+                    --seed: fixed:SIZE, uniform:MIN,MAX, lognormal:MEDIAN,SIGMA or \
+                    bimodal:SMALL,LARGE,LARGE_FRACTION. Implies --size-aware",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(OPTION_WEIGHT_COLUMN)
+                .long(OPTION_WEIGHT_COLUMN)
+                .help(
+                    "Read the weights of the values from the Nth (1-based) column of the \
+                    trace files. The lines without the column use the other weights. \
+                    Implies --size-aware",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(OPTION_VALUE_KIND)
+                .long(OPTION_VALUE_KIND)
+                .help(
+                    "The type of the values: arc-slice (Arc<[u8]>), vec (Vec<u8>), string \
+                    (String) or struct (a struct with several heap-allocated fields). \
+                    default: arc-slice",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(OPTION_VALUE_SIZE)
+                .long(OPTION_VALUE_SIZE)
+                .help("The size of the values in bytes, e.g. 128 or 4KiB. default: 128")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(OPTION_LATENCY)
+                .long(OPTION_LATENCY)
+                .help("Record the latency of each operation and print the percentiles"),
+        )
+        .arg(
+            Arg::new(OPTION_SIMULATE)
+                .long(OPTION_SIMULATE)
+                .help(
+                    "Replay the trace in order from a single client to get reproducible \
+                    hit ratios, without the producer/consumer overhead",
+                )
+                .conflicts_with_all(&[OPTION_NUM_CLIENTS, OPTION_LATENCY]),
+        )
+        .arg(
+            Arg::new(OPTION_PENDING_TASKS_INTERVAL)
+                .long(OPTION_PENDING_TASKS_INTERVAL)
+                .help(
+                    "In the simulate mode, run the pending tasks of the cache every N \
+                    operations. default: 64",
+                )
+                .takes_value(true)
+                .requires(OPTION_SIMULATE),
+        )
+        .arg(Arg::new(OPTION_OPT).long(OPTION_OPT).help(
+            "Also report the hit ratio of Belady's optimal policy, the upper bound \
+                    for any cache at each capacity",
+        ))
+        .arg(
+            Arg::new(OPTION_CACHES)
+                .long(OPTION_CACHES)
+                .help(
+                    "The caches to run, e.g. moka-sync,moka-async,quick_cache. Run \
+                    `mokabench list caches` to see the available caches. default: all",
+                )
+                .takes_value(true)
+                .multiple_values(true)
+                .use_value_delimiter(true),
+        )
+        .arg(
+            Arg::new(OPTION_POLICIES)
+                .long(OPTION_POLICIES)
+                .help(
+                    "Also run the reference policies from a single client: lru, lfu, arc, \
+                    lirs, w-tinylfu, random or all",
+                )
+                .takes_value(true)
+                .multiple_values(true)
+                .use_value_delimiter(true),
+        )
+        .arg(
+            Arg::new(OPTION_MRC)
+                .long(OPTION_MRC)
+                .help(
+                    "Plot the LRU miss ratio curve of each trace, computed \"exact\"ly or by \
+                    \"shards\" sampling, instead of running the caches",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(OPTION_MRC_SAMPLE_RATE)
+                .long(OPTION_MRC_SAMPLE_RATE)
+                .help("The sample rate of the shards method. default: 0.01")
+                .takes_value(true)
+                .requires(OPTION_MRC),
+        )
+        .arg(
+            Arg::new(OPTION_MRC_POINTS)
+                .long(OPTION_MRC_POINTS)
+                .help(
+                    "Also run the caches at N capacities selected on the miss ratio curve, \
+                    instead of the default capacities",
+                )
+                .takes_value(true)
+                .requires(OPTION_MRC)
+                .conflicts_with(OPTION_CAPACITIES),
+        )
+        .arg(
+            Arg::new(OPTION_SCENARIO)
+                .long(OPTION_SCENARIO)
+                .help(
+                    "Run the scenarios in the TOML file. Each scenario gives the traces, \
+                    capacities, clients, caches and workload options, and the other \
+                    options are taken from the command line",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(OPTION_OUTPUT_FORMAT)
+                .long(OPTION_OUTPUT_FORMAT)
+                .help("The format of the reports: csv, json (JSON Lines) or markdown. default: csv")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(OPTION_OUTPUT)
+                .short('o')
+                .long(OPTION_OUTPUT)
+                .help("Write the reports to the file instead of the stdout")
+                .takes_value(true),
+        );
 
-    if cfg!(not(feature = "moka-v08")) {
+    if cfg!(any(
+        feature = "moka-v012",
+        feature = "moka-local",
+        not(feature = "moka-v08")
+    )) {
         app = app.arg(
             Arg::new(OPTION_EVICTION_LISTENER)
                 .long(OPTION_EVICTION_LISTENER)
@@ -229,25 +772,191 @@ fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
         );
     }
 
-    if cfg!(not(any(feature = "moka-v09", feature = "moka-v08"))) {
+    if cfg!(any(
+        feature = "moka-v012",
+        feature = "moka-local",
+        not(any(feature = "moka-v09", feature = "moka-v08"))
+    )) {
         app = app.arg(Arg::new(OPTION_ENTRY_API).long(OPTION_ENTRY_API));
     }
 
-    if cfg!(not(any(
-        feature = "moka-v010",
-        feature = "moka-v09",
-        feature = "moka-v08"
-    ))) {
+    if cfg!(any(
+        feature = "moka-v012",
+        feature = "moka-local",
+        feature = "moka-v011"
+    )) {
         app = app.arg(Arg::new(OPTION_PER_KEY_EXPIRATION).long(OPTION_PER_KEY_EXPIRATION));
     }
 
-    let matches = app.get_matches();
+    app.subcommand(
+        Command::new(SUBCOMMAND_ANALYZE)
+            .about(
+                "Analyze the traces and report the unique keys, reuse distances, working \
+                set sizes and recommended capacities",
+            )
+            .args(trace_args()),
+    )
+    .subcommand(
+        Command::new(SUBCOMMAND_COMPARE)
+            .about(
+                "Compare two result files written with --output-format json, and exit \
+                with an error if any run regressed beyond the thresholds",
+            )
+            .arg(
+                Arg::new(ARG_OLD)
+                    .help("The result file of the baseline")
+                    .required(true),
+            )
+            .arg(
+                Arg::new(ARG_NEW)
+                    .help("The result file to compare with the baseline")
+                    .required(true),
+            )
+            .arg(
+                Arg::new(OPTION_THROUGHPUT_THRESHOLD)
+                    .long(OPTION_THROUGHPUT_THRESHOLD)
+                    .help("The largest allowed decrease of the throughput in percent. default: 5")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::new(OPTION_HIT_RATIO_THRESHOLD)
+                    .long(OPTION_HIT_RATIO_THRESHOLD)
+                    .help(
+                        "The largest allowed decrease of the hit ratio in percentage points. \
+                        default: 1",
+                    )
+                    .takes_value(true),
+            ),
+    )
+    .subcommand(
+        Command::new(SUBCOMMAND_CONVERT)
+            .about(
+                "Parse the trace files and write the entries to binary files next to them \
+                (e.g. S3.lis.mbt), which are replayed instead of the trace files",
+            )
+            .args(trace_args()),
+    )
+    .subcommand(
+        Command::new(SUBCOMMAND_LIST)
+            .about("List the available items")
+            .subcommand_required(true)
+            .subcommand(Command::new(SUBCOMMAND_CACHES).about(
+                "List the caches, whether they are compiled in, and their supported options",
+            )),
+    )
+}
 
-    let trace_files = matches
-        .values_of(OPTION_TRACE_FILE)
-        .unwrap()
-        .map(TraceFile::try_from)
-        .collect::<Result<Vec<TraceFile>, _>>()?;
+/// The options to choose the traces, shared by the benchmark and the `analyze`
+/// subcommand.
+fn trace_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new(OPTION_TRACE_FILE)
+            .alias(OPTION_TRACE_FILES)
+            .short('f')
+            .long(OPTION_TRACE_FILE)
+            .help(
+                "The trace file (e.g. s3, ds1, oltp) or a path to a custom trace file. \
+                default: s3",
+            )
+            .default_value("s3")
+            .default_missing_value("s3")
+            .takes_value(true)
+            .multiple_values(true)
+            .use_value_delimiter(true),
+        Arg::new(OPTION_TRACE_FORMAT)
+            .long(OPTION_TRACE_FORMAT)
+            .help(
+                "The format of custom trace files: \"arc\" (start and length per line) \
+                or \"lirs\" (one key per line). default: detected from each line",
+            )
+            .takes_value(true),
+        Arg::new(OPTION_GENERATOR)
+            .short('g')
+            .long(OPTION_GENERATOR)
+            .help(
+                "Use synthetic workload generators (zipf, uniform, hotspot, scan, latest) \
+                instead of the trace files",
+            )
+            .takes_value(true)
+            .multiple_values(true)
+            .use_value_delimiter(true),
+        Arg::new(OPTION_SKEW)
+            .long(OPTION_SKEW)
+            .help(
+                "The skew of the zipf and latest generators. Give multiple values to \
+                sweep them. default: 0.99",
+            )
+            .takes_value(true)
+            .multiple_values(true)
+            .use_value_delimiter(true),
+        Arg::new(OPTION_HOT_KEY_FRACTION)
+            .long(OPTION_HOT_KEY_FRACTION)
+            .help("The fraction of the hot keys for the hotspot generator. default: 0.2")
+            .takes_value(true),
+        Arg::new(OPTION_HOT_OP_FRACTION)
+            .long(OPTION_HOT_OP_FRACTION)
+            .help(
+                "The fraction of the operations on the hot keys for the hotspot \
+                generator. default: 0.8",
+            )
+            .takes_value(true),
+        Arg::new(OPTION_KEY_SPACE)
+            .long(OPTION_KEY_SPACE)
+            .help("The number of distinct keys of the generators. default: 1000000")
+            .takes_value(true),
+        Arg::new(OPTION_NUM_OPS)
+            .long(OPTION_NUM_OPS)
+            .help("The number of operations of the generators. default: 10000000")
+            .takes_value(true),
+        Arg::new(OPTION_SEED)
+            .long(OPTION_SEED)
+            .help(
+                "The random seed of the generators, the random operation mix and the \
+                weight distribution. default: 0",
+            )
+            .takes_value(true),
+    ]
+}
+
+fn create_config(matches: &ArgMatches) -> anyhow::Result<(Vec<TraceFile>, Config, ReportWriter)> {
+    let trace_files = parse_trace_files(matches)?;
+
+    let capacities = match matches.values_of(OPTION_CAPACITIES) {
+        None => None,
+        Some(v) => Some(parse_capacities(v)?),
+    };
+
+    let mrc_method = match matches.value_of(OPTION_MRC) {
+        None => None,
+        Some(v) => {
+            let sample_rate = match matches.value_of(OPTION_MRC_SAMPLE_RATE) {
+                None => mrc::DEFAULT_SAMPLE_RATE,
+                Some(v) => v.parse().with_context(|| {
+                    format!(r#"Cannot parse mrc-sample-rate "{v}" as a number"#)
+                })?,
+            };
+            Some(MrcMethod::parse(v, sample_rate)?)
+        }
+    };
+
+    let mrc_points =
+        match matches.value_of(OPTION_MRC_POINTS) {
+            None => None,
+            Some(v) => Some(v.parse().ok().filter(|n| *n > 0).with_context(|| {
+                format!(r#"Cannot parse mrc-points "{v}" as a positive integer"#)
+            })?),
+        };
+
+    // The capacities of custom trace files can be selected on the miss ratio curve.
+    if capacities.is_none() && mrc_method.is_none() {
+        if let Some(custom) = trace_files.iter().find(|f| f.is_custom()) {
+            anyhow::bail!(
+                "--{} is required for the custom trace file {}",
+                OPTION_CAPACITIES,
+                custom.path().display()
+            );
+        }
+    }
 
     let ttl_secs = match matches.value_of(OPTION_TTL) {
         None => None,
@@ -285,6 +994,56 @@ fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
         ),
     };
 
+    let time_limit = match matches.value_of(OPTION_DURATION) {
+        None => None,
+        Some(v) => Some(
+            humantime::parse_duration(v)
+                .with_context(|| format!(r#"Cannot parse duration "{v}", e.g. 30s or 2m"#))?,
+        ),
+    };
+
+    let max_ops = match matches.value_of(OPTION_MAX_OPS) {
+        None => None,
+        Some(v) => Some(
+            v.parse()
+                .ok()
+                .filter(|n| *n > 0)
+                .with_context(|| format!(r#"Cannot parse max-ops "{v}" as a positive integer"#))?,
+        ),
+    };
+
+    let streaming = matches.is_present(OPTION_STREAMING);
+    let channel_capacity = match matches.value_of(OPTION_CHANNEL_CAPACITY) {
+        None => DEFAULT_CHANNEL_CAPACITY,
+        Some(v) => v.parse().ok().filter(|n| *n > 0).with_context(|| {
+            format!(r#"Cannot parse channel-capacity "{v}" as a positive integer"#)
+        })?,
+    };
+
+    let iterations =
+        match matches.value_of(OPTION_ITERATIONS) {
+            None => 1,
+            Some(v) => v.parse().ok().filter(|n| *n > 0).with_context(|| {
+                format!(r#"Cannot parse iterations "{v}" as a positive integer"#)
+            })?,
+        };
+
+    let warmup = match matches.value_of(OPTION_WARMUP) {
+        None => 0,
+        Some(v) => v
+            .parse()
+            .with_context(|| format!(r#"Cannot parse warmup "{v}" as a non-negative integer"#))?,
+    };
+
+    let max_cv = match matches.value_of(OPTION_MAX_CV) {
+        None => DEFAULT_MAX_CV,
+        Some(v) => v
+            .parse()
+            .ok()
+            .filter(|cv: &f64| *cv >= 0.0)
+            .with_context(|| format!(r#"Cannot parse max-cv "{v}" as a non-negative number"#))?,
+    };
+
     let insertion_delay_micros = match matches.value_of(OPTION_INSERTION_DELAY) {
         None => None,
         Some(v) => Some(v.parse().with_context(|| {
@@ -298,6 +1057,81 @@ fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
     let invalidate_entries_if = matches.is_present(OPTION_INVALIDATE_IF);
     let iterate = matches.is_present(OPTION_ITERATE);
     let size_aware = matches.is_present(OPTION_SIZE_AWARE);
+    let weighted_ranges = matches.is_present(OPTION_WEIGHTED_RANGES);
+    let weight_dist = match matches.value_of(OPTION_WEIGHT_DIST) {
+        None => None,
+        Some(v) => Some(WeightDist::parse(v)?),
+    };
+    let weight_seed = match matches.value_of(OPTION_SEED) {
+        None => 0,
+        Some(v) => v
+            .parse()
+            .with_context(|| format!(r#"Cannot parse seed "{v}" as a positive integer"#))?,
+    };
+    let weight_column = match matches.value_of(OPTION_WEIGHT_COLUMN) {
+        None => None,
+        Some(v) => Some(v.parse().ok().filter(|n| *n > 0).with_context(|| {
+            format!(r#"Cannot parse weight-column "{v}" as a positive integer"#)
+        })?),
+    };
+    let value_kind = match matches.value_of(OPTION_VALUE_KIND) {
+        None => ValueKind::ArcSlice,
+        Some(v) => ValueKind::try_from(v)?,
+    };
+    let value_size = match matches.value_of(OPTION_VALUE_SIZE) {
+        None => DEFAULT_VALUE_SIZE,
+        Some(v) => weight::parse_byte_size(v)
+            .and_then(|n| usize::try_from(n).ok())
+            .filter(|n| *n > 0)
+            .with_context(|| format!(r#"Cannot parse value-size "{v}" as a size in bytes"#))?,
+    };
+    let latency = matches.is_present(OPTION_LATENCY);
+    let simulate = matches.is_present(OPTION_SIMULATE);
+    let opt = matches.is_present(OPTION_OPT);
+
+    let caches = match matches.values_of(OPTION_CACHES) {
+        None => None,
+        Some(v) => Some(CacheKind::parse_list(v)?),
+    };
+
+    let mut policies = Vec::new();
+    for v in matches.values_of(OPTION_POLICIES).into_iter().flatten() {
+        if v.trim().eq_ignore_ascii_case("all") {
+            policies.extend(ReferencePolicy::ALL);
+        } else {
+            policies.push(ReferencePolicy::try_from(v)?);
+        }
+    }
+
+    let pending_tasks_interval = match matches.value_of(OPTION_PENDING_TASKS_INTERVAL) {
+        None => DEFAULT_PENDING_TASKS_INTERVAL,
+        Some(v) => v.parse().ok().filter(|n| *n > 0).with_context(|| {
+            format!(r#"Cannot parse pending-tasks-interval "{v}" as a positive integer"#)
+        })?,
+    };
+
+    let op_mix = match matches.values_of(OPTION_MIX) {
+        None => None,
+        Some(pairs) => {
+            let mode = match matches.value_of(OPTION_MIX_MODE) {
+                None | Some("periodic") => MixMode::Periodic,
+                Some("random") => {
+                    let seed = match matches.value_of(OPTION_SEED) {
+                        None => 0,
+                        Some(v) => v.parse().with_context(|| {
+                            format!(r#"Cannot parse seed "{v}" as a positive integer"#)
+                        })?,
+                    };
+                    MixMode::Random { seed }
+                }
+                Some(v) => anyhow::bail!(
+                    r#"mix-mode must be "periodic" or "random", but got "{}""#,
+                    v
+                ),
+            };
+            Some(OpMix::parse(pairs, mode)?)
+        }
+    };
 
     // Since Moka v0.10
     let entry_api = matches.is_present(OPTION_ENTRY_API);
@@ -307,7 +1141,11 @@ fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
 
     let mut eviction_listener = RemovalNotificationMode::None;
 
-    if cfg!(not(feature = "moka-v08")) {
+    if cfg!(any(
+        feature = "moka-v012",
+        feature = "moka-local",
+        not(feature = "moka-v08")
+    )) {
         if let Some(v) = matches.value_of(OPTION_EVICTION_LISTENER) {
             match v {
                 "immediate" => {
@@ -325,7 +1163,7 @@ fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
                 }
                 "queued" => {
                     eviction_listener = RemovalNotificationMode::Queued;
-                    if cfg!(feature = "moka-v012") {
+                    if cfg!(any(feature = "moka-v012", feature = "moka-local")) {
                         eprintln!(
                             "WARNING: eviction_listener = \"queued\" is not supported by \
                             the async cache. \"immediate\" mode will be used for it."
@@ -342,7 +1180,14 @@ fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
         }
     }
 
-    if !entry_api && insert_once && cfg!(not(any(feature = "moka-v08", feature = "moka-v09"))) {
+    if !entry_api
+        && insert_once
+        && cfg!(any(
+            feature = "moka-v012",
+            feature = "moka-local",
+            not(any(feature = "moka-v08", feature = "moka-v09"))
+        ))
+    {
         eprintln!("\nWARNING: Testing Moka's entry API is disabled by default. Use --entry-api to enable it.\n");
     }
 
@@ -351,22 +1196,187 @@ fn create_config() -> anyhow::Result<(Vec<TraceFile>, Config)> {
     }
 
     let mut config = Config::new(
-        trace_files[0],
+        trace_files[0].clone(),
         ttl_secs,
         tti_secs,
         num_clients,
         repeat,
         insertion_delay_micros,
     );
+    config.set_time_limit(time_limit);
+    config.set_max_ops(max_ops);
+    config.set_streaming(streaming, channel_capacity);
+    config.set_iterations(iterations, warmup, max_cv);
     config.set_insert_once(insert_once);
     config.set_invalidate(invalidate);
     config.set_invalidate_all(invalidate_all);
     config.set_invalidate_entries_if(invalidate_entries_if);
     config.set_iterate(iterate);
+    config.set_op_mix(op_mix);
+    config.set_latency(latency);
+    config.set_simulate(simulate, pending_tasks_interval);
+    config.set_opt(opt);
+    config.set_caches(caches);
+    config.set_policies(policies);
+    config.set_mrc(mrc_method, mrc_points);
     config.set_eviction_listener(eviction_listener);
     config.set_size_aware(size_aware);
+    config.set_weighted_ranges(weighted_ranges);
+    config.set_weight_dist(weight_dist, weight_seed);
+    config.set_weight_column(weight_column);
+    config.set_value(value_kind, value_size);
+    match capacities {
+        Some((capacities, true)) => config.set_capacities_in_bytes(capacities),
+        capacities => config.set_capacities(capacities.map(|(capacities, _)| capacities)),
+    }
     config.set_entry_api(entry_api);
     config.set_per_key_expiration(per_key_expiration);
 
-    Ok((trace_files, config))
+    let output_format = match matches.value_of(OPTION_OUTPUT_FORMAT) {
+        None => OutputFormat::Csv,
+        Some(v) => OutputFormat::try_from(v)?,
+    };
+    let writer = ReportWriter::new(
+        output_format,
+        matches.value_of(OPTION_OUTPUT).map(std::path::Path::new),
+    )?;
+
+    Ok((trace_files, config, writer))
+}
+
+/// Parses the capacities in number of entries, or in bytes if they have units.
+/// Returns the capacities and whether they are in bytes.
+fn parse_capacities<'a>(
+    values: impl Iterator<Item = &'a str>,
+) -> anyhow::Result<(Vec<usize>, bool)> {
+    let values = values.collect::<Vec<_>>();
+    let in_bytes = values.iter().any(|v| weight::has_byte_unit(v));
+    if in_bytes && !values.iter().all(|v| weight::has_byte_unit(v)) {
+        anyhow::bail!(
+            "The capacities must be all in number of entries or all in bytes with units, \
+            but got {}",
+            values.join(" ")
+        );
+    }
+
+    let capacities = values
+        .into_iter()
+        .map(|v| {
+            let capacity = if in_bytes {
+                weight::parse_byte_size(v)
+                    .and_then(|n| usize::try_from(n).ok())
+                    .filter(|n| *n > 0)
+            } else {
+                v.parse().ok()
+            };
+            capacity.with_context(|| {
+                if in_bytes {
+                    format!(r#"Cannot parse capacity "{v}" as a size in bytes"#)
+                } else {
+                    format!(r#"Cannot parse capacity "{v}" as a positive integer"#)
+                }
+            })
+        })
+        .collect::<anyhow::Result<Vec<usize>>>()?;
+    Ok((capacities, in_bytes))
+}
+
+fn parse_trace_files(matches: &ArgMatches) -> anyhow::Result<Vec<TraceFile>> {
+    let mut trace_files = matches
+        .values_of(OPTION_TRACE_FILE)
+        .unwrap()
+        .map(TraceFile::try_from)
+        .collect::<Result<Vec<TraceFile>, _>>()?;
+
+    if let Some(workloads) = parse_workloads(matches)? {
+        // Unless the trace files are given explicitly, use only the generators.
+        if matches.occurrences_of(OPTION_TRACE_FILE) == 0 {
+            trace_files.clear();
+        }
+        trace_files.extend(workloads.into_iter().map(TraceFile::Synthetic));
+    }
+
+    if let Some(v) = matches.value_of(OPTION_TRACE_FORMAT) {
+        let format = TraceFileGroup::try_from(v)?;
+        for trace_file in &mut trace_files {
+            if let TraceFile::Custom(custom) = trace_file {
+                custom.format = Some(format);
+            }
+        }
+    }
+
+    if std::env::var_os(DATASET_DIR_ENV).is_some() {
+        eprintln!(
+            "Using the dataset directory {} (set by {}).",
+            mokabench::trace_file::dataset_dir().display(),
+            DATASET_DIR_ENV
+        );
+    }
+
+    Ok(trace_files)
+}
+
+fn parse_workloads(matches: &ArgMatches) -> anyhow::Result<Option<Vec<Workload>>> {
+    let Some(generators) = matches.values_of(OPTION_GENERATOR) else {
+        return Ok(None);
+    };
+
+    let skews = match matches.values_of(OPTION_SKEW) {
+        None => vec![generator::DEFAULT_SKEW],
+        Some(v) => v
+            .map(|v| {
+                v.parse()
+                    .with_context(|| format!(r#"Cannot parse skew "{v}" as a number"#))
+            })
+            .collect::<Result<Vec<f64>, _>>()?,
+    };
+
+    let hot_key_fraction = match matches.value_of(OPTION_HOT_KEY_FRACTION) {
+        None => generator::DEFAULT_HOT_KEY_FRACTION,
+        Some(v) => v
+            .parse()
+            .with_context(|| format!(r#"Cannot parse hot-key-fraction "{v}" as a number"#))?,
+    };
+
+    let hot_op_fraction = match matches.value_of(OPTION_HOT_OP_FRACTION) {
+        None => generator::DEFAULT_HOT_OP_FRACTION,
+        Some(v) => v
+            .parse()
+            .with_context(|| format!(r#"Cannot parse hot-op-fraction "{v}" as a number"#))?,
+    };
+
+    let key_space = match matches.value_of(OPTION_KEY_SPACE) {
+        None => generator::DEFAULT_KEY_SPACE,
+        Some(v) => v
+            .parse()
+            .with_context(|| format!(r#"Cannot parse key-space "{v}" as a positive integer"#))?,
+    };
+
+    let num_ops = match matches.value_of(OPTION_NUM_OPS) {
+        None => generator::DEFAULT_NUM_OPS,
+        Some(v) => v
+            .parse()
+            .with_context(|| format!(r#"Cannot parse num-ops "{v}" as a positive integer"#))?,
+    };
+
+    let seed = match matches.value_of(OPTION_SEED) {
+        None => 0,
+        Some(v) => v
+            .parse()
+            .with_context(|| format!(r#"Cannot parse seed "{v}" as a positive integer"#))?,
+    };
+
+    let mut workloads = Vec::new();
+    for name in generators {
+        workloads.extend(Workload::parse_each_skew(
+            name,
+            &skews,
+            hot_key_fraction,
+            hot_op_fraction,
+            key_space,
+            num_ops,
+            seed,
+        )?);
+    }
+    Ok(Some(workloads))
 }
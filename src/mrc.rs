@@ -0,0 +1,201 @@
+//! Miss ratio curves (MRC) of LRU. The hit ratios for all capacities are computed
+//! from the reuse distances in one pass over the trace, either exactly or by
+//! sampling the keys as SHARDS does (C. A. Waldspurger et al., "Efficient MRC
+//! Construction with SHARDS", FAST '15).
+
+use std::{
+    fmt,
+    hash::BuildHasher,
+    io::{self, Write},
+};
+
+use crate::{
+    analyze::{self, StackDistances},
+    cache::DefaultHasher,
+    config::Config,
+    load_gen,
+};
+
+pub const DEFAULT_SAMPLE_RATE: f64 = 0.01;
+
+// A key is sampled when its hash modulo this value is smaller than the sample
+// rate times this value.
+const SAMPLE_MODULUS: u64 = 1 << 24;
+
+const PLOT_WIDTH: usize = 64;
+const PLOT_HEIGHT: usize = 21;
+const NUM_TABLE_POINTS: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MrcMethod {
+    /// Computes the reuse distances of all accesses.
+    Exact,
+    /// Computes the reuse distances of the accesses to a sample of the keys, and
+    /// scales them by the sample rate. It needs much less memory for large traces.
+    Shards { sample_rate: f64 },
+}
+
+impl MrcMethod {
+    pub fn parse(name: &str, sample_rate: f64) -> anyhow::Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "exact" => Ok(Self::Exact),
+            "shards" => {
+                if !(sample_rate > 0.0 && sample_rate <= 1.0) {
+                    anyhow::bail!(
+                        "The sample rate must be greater than 0 and at most 1, but got {sample_rate}"
+                    );
+                }
+                Ok(Self::Shards { sample_rate })
+            }
+            _ => anyhow::bail!(r#"Unknown MRC method "{name}". It must be "exact" or "shards""#),
+        }
+    }
+
+    fn sample_rate(&self) -> f64 {
+        match self {
+            Self::Exact => 1.0,
+            Self::Shards { sample_rate } => *sample_rate,
+        }
+    }
+}
+
+impl fmt::Display for MrcMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact => write!(f, "exact"),
+            Self::Shards { sample_rate } => write!(f, "shards(rate={sample_rate})"),
+        }
+    }
+}
+
+pub struct MissRatioCurve {
+    pub trace: String,
+    pub method: MrcMethod,
+    /// The number of accesses used to compute the curve.
+    pub sampled_accesses: u64,
+    /// The (estimated) number of unique keys in the trace. The hit ratio does not
+    /// increase beyond this capacity.
+    pub unique_keys: usize,
+    distance_counts: Vec<u64>,
+}
+
+impl MissRatioCurve {
+    /// Reads the trace and computes the curve.
+    pub fn compute(config: &Config, method: MrcMethod) -> anyhow::Result<Self> {
+        let sample_rate = method.sample_rate();
+        let threshold = (sample_rate * SAMPLE_MODULUS as f64) as u64;
+
+        let mut keys = Vec::new();
+        for entry in load_gen::trace_entries(config)? {
+            for key in entry?.range() {
+                if method == MrcMethod::Exact
+                    || DefaultHasher.hash_one(key) % SAMPLE_MODULUS < threshold
+                {
+                    keys.push(key);
+                }
+            }
+        }
+
+        let mut distances = StackDistances::new(keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            distances.record(i, *key);
+        }
+
+        Ok(Self {
+            trace: config.trace_file.to_string(),
+            method,
+            sampled_accesses: keys.len() as u64,
+            unique_keys: (distances.unique_keys() as f64 / sample_rate).round() as usize,
+            distance_counts: distances.counts,
+        })
+    }
+
+    /// Returns the hit ratio of an LRU cache with the capacity.
+    pub fn hit_ratio(&self, capacity: usize) -> f64 {
+        let end = ((capacity as f64 * self.method.sample_rate()).ceil() as usize)
+            .min(self.distance_counts.len());
+        let hits: u64 = self.distance_counts[..end].iter().sum();
+        hits as f64 / self.sampled_accesses as f64
+    }
+
+    /// Selects `n` capacities, at which the hit ratio reaches evenly spaced
+    /// fractions of the maximum hit ratio. For example, 25%, 50% and 75% of it for
+    /// `n = 3`. They are rounded up to two significant digits.
+    pub fn select_capacities(&self, n: usize) -> Vec<usize> {
+        let fractions = (1..=n)
+            .map(|i| i as f64 / (n + 1) as f64)
+            .collect::<Vec<_>>();
+        let mut capacities = analyze::capacities_at_fractions(
+            &self.distance_counts,
+            &fractions,
+            self.method.sample_rate(),
+        )
+        .into_iter()
+        .map(analyze::round_up_capacity)
+        .collect::<Vec<_>>();
+        capacities.dedup();
+        capacities
+    }
+
+    /// Writes the curve as an ASCII plot with the capacity in log scale, followed
+    /// by a table of the hit ratios.
+    pub fn write_plot(&self, out: &mut impl Write) -> io::Result<()> {
+        let max_capacity = self.unique_keys.max(2);
+        // The capacity at the column, from 1 to `max_capacity` in log scale.
+        let capacity_at = |col: usize, num_cols: usize| {
+            let exp = (max_capacity as f64).ln() * col as f64 / (num_cols - 1) as f64;
+            exp.exp().round() as usize
+        };
+
+        // The row of the point at each column. Row 0 is the top (100%).
+        let rows = (0..PLOT_WIDTH)
+            .map(|col| {
+                let hit_ratio = self.hit_ratio(capacity_at(col, PLOT_WIDTH));
+                PLOT_HEIGHT - 1 - (hit_ratio * (PLOT_HEIGHT - 1) as f64).round() as usize
+            })
+            .collect::<Vec<_>>();
+
+        writeln!(
+            out,
+            "LRU hit ratio vs capacity ({}, {})",
+            self.trace, self.method
+        )?;
+        for row in 0..PLOT_HEIGHT {
+            let label = if row % 5 == 0 {
+                let percent = (PLOT_HEIGHT - 1 - row) * 100 / (PLOT_HEIGHT - 1);
+                format!("{percent:>3}%")
+            } else {
+                String::new()
+            };
+            let line = rows
+                .iter()
+                .map(|r| if *r == row { '*' } else { ' ' })
+                .collect::<String>();
+            writeln!(out, "{label:>4} |{line}")?;
+        }
+        writeln!(out, "     +{}", "-".repeat(PLOT_WIDTH))?;
+        let mid = capacity_at(PLOT_WIDTH / 2, PLOT_WIDTH).to_string();
+        let max = max_capacity.to_string();
+        let gap1 = PLOT_WIDTH / 2 - 1;
+        let gap2 = (PLOT_WIDTH / 2).saturating_sub(mid.len() + max.len()) + 1;
+        writeln!(out, "      1{:gap1$}{mid}{:gap2$}{max}", "", "")?;
+        writeln!(out)?;
+
+        writeln!(out, "  {:>12}  {:>10}", "Capacity", "Hit Ratio")?;
+        let mut prev = 0;
+        for i in 0..NUM_TABLE_POINTS {
+            let capacity = capacity_at(i, NUM_TABLE_POINTS);
+            if capacity == prev {
+                continue;
+            }
+            prev = capacity;
+            writeln!(
+                out,
+                "  {:>12}  {:>9.3}%",
+                capacity,
+                self.hit_ratio(capacity) * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
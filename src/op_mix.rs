@@ -0,0 +1,191 @@
+//! The operation mix, which decides the cache operation for each trace entry.
+
+use std::fmt;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Operation {
+    /// `get`, and `insert` on a miss.
+    Get,
+    /// `get_with` or the entry API (`--insert-once`).
+    GetWith,
+    /// `insert` without `get`.
+    Update,
+    Invalidate,
+    InvalidateAll,
+    InvalidateEntriesIf,
+    Iterate,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 7] = [
+        Self::Get,
+        Self::GetWith,
+        Self::Update,
+        Self::Invalidate,
+        Self::InvalidateAll,
+        Self::InvalidateEntriesIf,
+        Self::Iterate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Get => "get",
+            Self::GetWith => "get-with",
+            Self::Update => "update",
+            Self::Invalidate => "invalidate",
+            Self::InvalidateAll => "invalidate-all",
+            Self::InvalidateEntriesIf => "invalidate-if",
+            Self::Iterate => "iterate",
+        }
+    }
+}
+
+impl TryFrom<&str> for Operation {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim().to_lowercase();
+        Self::ALL
+            .iter()
+            .find(|op| op.name() == value)
+            .copied()
+            .ok_or_else(|| {
+                let names = Self::ALL.iter().map(|op| op.name()).collect::<Vec<_>>();
+                anyhow::anyhow!(
+                    r#"Unknown operation "{}". It must be one of {}"#,
+                    value,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MixMode {
+    /// Operations are interleaved deterministically, so that each of them appears
+    /// at even intervals in proportion to its weight.
+    Periodic,
+    /// Operations are chosen randomly in proportion to their weights.
+    Random { seed: u64 },
+}
+
+/// The relative weights of the operations, e.g. `get=80,update=10,invalidate=10`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpMix {
+    weights: Vec<(Operation, f64)>,
+    pub mode: MixMode,
+}
+
+impl OpMix {
+    /// Parses the weights from `op=weight` pairs.
+    pub fn parse<'a>(
+        pairs: impl IntoIterator<Item = &'a str>,
+        mode: MixMode,
+    ) -> anyhow::Result<Self> {
+        let mut weights: Vec<(Operation, f64)> = Vec::new();
+        // Includes the operations with weight 0, which are not in `weights`.
+        let mut seen_ops = Vec::new();
+        for pair in pairs {
+            let Some((op, weight)) = pair.split_once('=') else {
+                anyhow::bail!(r#"Expected "op=weight" in the operation mix, but got "{pair}""#);
+            };
+            let op = Operation::try_from(op)?;
+            let weight = weight
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|w| w.is_finite() && *w >= 0.0)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(r#"Cannot parse the weight of "{pair}" as a positive number"#)
+                })?;
+            if seen_ops.contains(&op) {
+                anyhow::bail!(r#"The operation "{}" is given twice"#, op.name());
+            }
+            seen_ops.push(op);
+            if weight > 0.0 {
+                weights.push((op, weight));
+            }
+        }
+
+        if weights.is_empty() {
+            anyhow::bail!("The operation mix must have at least one operation with weight > 0");
+        }
+
+        Ok(Self { weights, mode })
+    }
+
+    pub fn contains(&self, op: Operation) -> bool {
+        self.weights.iter().any(|(o, _)| *o == op)
+    }
+
+    pub(crate) fn selector(&self) -> OpSelector {
+        OpSelector::new(self)
+    }
+}
+
+impl fmt::Display for OpMix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs = self
+            .weights
+            .iter()
+            .map(|(op, w)| format!("{}={}", op.name(), w))
+            .collect::<Vec<_>>();
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+/// Selects the operation for each trace entry according to an `OpMix`.
+pub(crate) struct OpSelector {
+    ops: Vec<Operation>,
+    weights: Vec<f64>,
+    total_weight: f64,
+    // For the periodic mode. The current weights of the smooth weighted
+    // round-robin algorithm.
+    current: Vec<f64>,
+    // For the random mode.
+    rng: Option<StdRng>,
+}
+
+impl OpSelector {
+    fn new(mix: &OpMix) -> Self {
+        let (ops, weights): (Vec<_>, Vec<_>) = mix.weights.iter().copied().unzip();
+        let rng = match mix.mode {
+            MixMode::Periodic => None,
+            MixMode::Random { seed } => Some(StdRng::seed_from_u64(seed)),
+        };
+
+        Self {
+            current: vec![0.0; ops.len()],
+            total_weight: weights.iter().sum(),
+            ops,
+            weights,
+            rng,
+        }
+    }
+
+    pub(crate) fn next_op(&mut self) -> Operation {
+        if let Some(rng) = &mut self.rng {
+            let mut r = rng.gen_range(0.0..self.total_weight);
+            for (op, w) in self.ops.iter().zip(&self.weights) {
+                if r < *w {
+                    return *op;
+                }
+                r -= w;
+            }
+            return *self.ops.last().unwrap();
+        }
+
+        // Smooth weighted round-robin.
+        let mut selected = 0;
+        for (i, w) in self.weights.iter().enumerate() {
+            self.current[i] += w;
+            if self.current[i] > self.current[selected] {
+                selected = i;
+            }
+        }
+        self.current[selected] -= self.total_weight;
+        self.ops[selected]
+    }
+}
@@ -0,0 +1,244 @@
+//! Belady's optimal (OPT) replacement policy. It knows the whole trace in
+//! advance, and always evicts the entry that will be used again farthest in the
+//! future. Its hit ratio is the upper bound for any cache on the same trace and
+//! capacity.
+//!
+//! All trace entries are treated as reads (get, and insert on a miss), and the
+//! other operations such as invalidations are not simulated.
+
+use std::{
+    collections::{BinaryHeap, HashMap},
+    time::Instant,
+};
+
+use crate::{
+    cache::{self, DefaultHasher, Key},
+    config::Config,
+    load_gen, Report,
+};
+
+/// The next use of an entry that is never accessed again.
+const NEVER: usize = usize::MAX;
+
+struct Access {
+    key: Key,
+    // The index of the next access to the same key, or `NEVER`.
+    next_use: usize,
+    // The policy weight of the value if it is inserted by this access. Always 1
+    // unless the size-aware mode is enabled.
+    weight: u32,
+}
+
+/// The block accesses of a trace, with the position of the next use of each
+/// access.
+pub struct Belady {
+    accesses: Vec<Access>,
+    has_eviction_counts: bool,
+}
+
+impl Belady {
+    /// Reads the trace (`config.repeat` times) and expands the entries into block
+    /// accesses.
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let mut accesses = Vec::new();
+        for _ in 0..(config.repeat.unwrap_or(1)) {
+            for entry in load_gen::trace_entries(config)? {
+                let entry = entry?;
+                for (block, req) in entry.requests() {
+                    let weight = if config.size_aware {
+                        cache::policy_weight(config, req)
+                    } else {
+                        1
+                    };
+                    accesses.push(Access {
+                        key: block,
+                        next_use: NEVER,
+                        weight,
+                    });
+                }
+            }
+        }
+
+        Ok(Self::from_accesses(
+            accesses,
+            config.is_eviction_listener_enabled(),
+        ))
+    }
+
+    /// Fills in the next use of each access.
+    fn from_accesses(mut accesses: Vec<Access>, has_eviction_counts: bool) -> Self {
+        // Walk backward to find the next use of each access.
+        let mut last_seen = HashMap::with_hasher(DefaultHasher);
+        for (i, access) in accesses.iter_mut().enumerate().rev() {
+            if let Some(next) = last_seen.insert(access.key, i) {
+                access.next_use = next;
+            }
+        }
+
+        Self {
+            accesses,
+            has_eviction_counts,
+        }
+    }
+
+    /// Replays the accesses with the given max capacity (see
+    /// `Config::max_capacity`) and returns the report.
+    ///
+    /// In the size-aware mode, the max capacity is the total weight as the other
+    /// caches, and entries are evicted farthest-next-use first until the new one
+    /// fits. Note that this is a heuristic, as the weighted OPT is NP-hard, so its
+    /// hit ratio is a close estimate rather than a strict upper bound.
+    pub fn run(&self, max_cap: u64) -> Report {
+        let mut report = Report::new("Optimal (Belady)", max_cap, None);
+        report.has_eviction_counts = self.has_eviction_counts;
+
+        // The key -> (next use, weight) of the cached entries.
+        let mut entries = HashMap::with_hasher(DefaultHasher);
+        // A max-heap of (next use, key). Entries whose next use has changed are
+        // left in the heap and skipped when they are popped.
+        let mut heap = BinaryHeap::new();
+        let mut total_weight = 0u64;
+
+        let instant = Instant::now();
+        for access in &self.accesses {
+            report.read_count += 1;
+            if let Some((next_use, _)) = entries.get_mut(&access.key) {
+                report.hit_count += 1;
+                *next_use = access.next_use;
+                heap.push((access.next_use, access.key));
+                continue;
+            }
+
+            report.insert_count += 1;
+            let weight = access.weight as u64;
+            // Do not cache an entry that will never be used again, or that does
+            // not fit in the cache at all.
+            if access.next_use == NEVER || weight > max_cap {
+                continue;
+            }
+
+            // Choose the entries to evict, farthest first, before changing the
+            // cache. If the new entry will be used later than any of them,
+            // evicting them is not better than not caching the new one, so the
+            // cache is left as it is.
+            let mut victims = Vec::new();
+            let mut freed_weight = 0u64;
+            let mut admitted = true;
+            while total_weight - freed_weight + weight > max_cap {
+                let (farthest, key) =
+                    peek_farthest(&mut heap, &entries).expect("the cache must not be empty");
+                if farthest <= access.next_use {
+                    admitted = false;
+                    break;
+                }
+                heap.pop();
+                freed_weight += entries[&key].1;
+                victims.push((farthest, key));
+            }
+
+            if !admitted {
+                heap.extend(victims);
+                continue;
+            }
+            for (_, key) in victims {
+                let (_, evicted_weight) = entries.remove(&key).unwrap();
+                total_weight -= evicted_weight;
+                report.eviction_count += 1;
+            }
+            entries.insert(access.key, (access.next_use, weight));
+            heap.push((access.next_use, access.key));
+            total_weight += weight;
+        }
+        report.duration = Some(instant.elapsed());
+
+        report
+    }
+}
+
+/// Returns the cached entry with the farthest next use, discarding the stale
+/// elements at the top of the heap.
+fn peek_farthest(
+    heap: &mut BinaryHeap<(usize, Key)>,
+    entries: &HashMap<Key, (usize, u64), DefaultHasher>,
+) -> Option<(usize, Key)> {
+    while let Some(&(next_use, key)) = heap.peek() {
+        if entries
+            .get(&key)
+            .is_some_and(|(current, _)| *current == next_use)
+        {
+            return Some((next_use, key));
+        }
+        heap.pop();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Access, Belady, NEVER};
+    use crate::cache::Key;
+
+    fn belady(accesses: &[(Key, u32)]) -> Belady {
+        let accesses = accesses
+            .iter()
+            .map(|&(key, weight)| Access {
+                key,
+                next_use: NEVER,
+                weight,
+            })
+            .collect();
+        Belady::from_accesses(accesses, true)
+    }
+
+    fn unweighted(keys: &[Key]) -> Belady {
+        belady(&keys.iter().map(|&k| (k, 1)).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn textbook_reference_string() {
+        // The reference string and 3 frames of the OPT example in the operating
+        // system textbooks, where OPT has 9 page faults (11 hits). Here, key 4 at
+        // index 7 is never used again, so it is not admitted, and key 0 stays in
+        // the cache for its hit at index 10.
+        let belady = unweighted(&[7, 0, 1, 2, 0, 3, 0, 4, 2, 3, 0, 3, 2, 1, 2, 0, 1, 7, 0, 1]);
+        let report = belady.run(3);
+        assert_eq!(report.read_count, 20);
+        assert_eq!(report.hit_count, 12);
+        assert_eq!(report.insert_count, 8);
+        // Evicted 7 for 2, 1 for 3, and 3 for 1.
+        assert_eq!(report.eviction_count, 3);
+    }
+
+    #[test]
+    fn never_reused_key_is_not_admitted() {
+        // Key 3 is never used again, so it does not evict key 1 or 2.
+        let report = unweighted(&[1, 2, 3, 1, 2]).run(2);
+        assert_eq!(report.hit_count, 2);
+        assert_eq!(report.insert_count, 3);
+        assert_eq!(report.eviction_count, 0);
+    }
+
+    #[test]
+    fn weighted_entry_is_rejected() {
+        // The next uses: key 1 at 7, key 2 at 4, key 3 at 5, and key 4 at 6. At
+        // index 2, key 3 (weight 3) needs both key 1 and key 2 (weight 2 each)
+        // evicted, but key 2 will be used before key 3, so key 3 is not cached and
+        // the cache is unchanged. At index 3, key 4 must find key 1, which was
+        // popped as a victim of key 3, in the heap to evict it.
+        let report = belady(&[
+            (1, 2),
+            (2, 2),
+            (3, 3),
+            (4, 2),
+            (2, 2),
+            (3, 3),
+            (4, 2),
+            (1, 2),
+        ])
+        .run(4);
+        // Key 2 at index 4 and key 4 at index 6.
+        assert_eq!(report.hit_count, 2);
+        assert_eq!(report.insert_count, 6);
+        assert_eq!(report.eviction_count, 1);
+    }
+}
@@ -0,0 +1,338 @@
+//! Writes the reports in CSV, JSON or Markdown format to the stdout or a file.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    mem,
+    path::Path,
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, report::IterationStats, Report};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Comma separated values. This is the default.
+    Csv,
+    /// JSON Lines. Each line is a JSON object of a `RunRecord`.
+    Json,
+    /// Markdown tables.
+    Markdown,
+}
+
+impl TryFrom<&str> for OutputFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim().to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => Err(anyhow::anyhow!(
+                r#"Unknown output format "{}". It must be "csv", "json" or "markdown""#,
+                value
+            )),
+        }
+    }
+}
+
+/// A record written in the JSON format, one per `Report`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// The label of the scenario.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub trace: String,
+    pub config: serde_json::Value,
+    pub report: ReportRecord,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportRecord {
+    pub name: String,
+    /// The version of the cache, e.g. `0.12` for Moka.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub capacity: u64,
+    pub num_workers: Option<u16>,
+    pub insert_count: u64,
+    pub read_count: u64,
+    pub hit_count: u64,
+    pub hit_ratio: Option<f64>,
+    // The following three are available only when the eviction listener is enabled.
+    pub invalidation_count: Option<u64>,
+    pub eviction_count: Option<u64>,
+    pub expiration_count: Option<u64>,
+    pub duration_secs: Option<f64>,
+    /// The reads and inserts on the cache, and the invalidation and iteration
+    /// commands.
+    #[serde(default)]
+    pub total_ops: u64,
+    #[serde(default)]
+    pub ops_per_sec: Option<f64>,
+    #[serde(default)]
+    pub ns_per_op: Option<f64>,
+    /// The number of commands and the throughput of each operation type.
+    #[serde(default)]
+    pub operations: Vec<OperationRecord>,
+    pub latencies: Option<Vec<LatencyRecord>>,
+    /// Available only with `--streaming`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub streaming: Option<StreamingRecord>,
+    /// The statistics over the iterations. Available only with `--iterations`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<IterationStats>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OperationRecord {
+    pub operation: String,
+    pub count: u64,
+    pub ops_per_sec: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamingRecord {
+    pub channel_capacity: usize,
+    pub producer_stall_secs: f64,
+    pub client_wait_secs: f64,
+}
+
+/// Latency percentiles of an operation type, in nanoseconds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LatencyRecord {
+    pub operation: String,
+    pub count: u64,
+    pub p50_ns: u64,
+    pub p90_ns: u64,
+    pub p99_ns: u64,
+    pub p99_9_ns: u64,
+    pub max_ns: u64,
+}
+
+impl From<&Report> for ReportRecord {
+    fn from(report: &Report) -> Self {
+        let eviction_count = |count| report.has_eviction_counts.then_some(count);
+        let hit_ratio = report.hit_ratio();
+
+        Self {
+            name: report.name.clone(),
+            version: report.version.clone(),
+            capacity: report.capacity,
+            num_workers: report.num_workers,
+            insert_count: report.insert_count,
+            read_count: report.read_count,
+            hit_count: report.hit_count,
+            hit_ratio: (!hit_ratio.is_nan()).then_some(hit_ratio),
+            invalidation_count: eviction_count(report.invalidation_count),
+            eviction_count: eviction_count(report.eviction_count),
+            expiration_count: eviction_count(report.expiration_count),
+            duration_secs: report.duration.map(|d| d.as_secs_f64()),
+            total_ops: report.total_ops(),
+            ops_per_sec: report.ops_per_sec(),
+            ns_per_op: report.nanos_per_op(),
+            operations: report
+                .operation_counts()
+                .map(|(op, count)| OperationRecord {
+                    operation: op.name().to_string(),
+                    count,
+                    ops_per_sec: report.per_sec(count),
+                })
+                .collect(),
+            latencies: report.latencies.as_ref().map(|latencies| {
+                latencies
+                    .summaries()
+                    .map(|s| LatencyRecord {
+                        operation: s.operation.name().to_string(),
+                        count: s.count,
+                        p50_ns: s.p50,
+                        p90_ns: s.p90,
+                        p99_ns: s.p99,
+                        p99_9_ns: s.p999,
+                        max_ns: s.max,
+                    })
+                    .collect()
+            }),
+            streaming: report.streaming.as_ref().map(|s| StreamingRecord {
+                channel_capacity: s.channel_capacity,
+                producer_stall_secs: s.producer_stall.as_secs_f64(),
+                client_wait_secs: s.client_wait.as_secs_f64(),
+            }),
+            iterations: report.iterations.clone(),
+        }
+    }
+}
+
+pub struct ReportWriter {
+    format: OutputFormat,
+    out: Box<dyn Write>,
+    to_stdout: bool,
+    // The latency rows of the current trace, which are written as a separate
+    // table at the end of the trace.
+    latency_rows: Vec<Vec<String>>,
+    // The rows of the per-operation throughput.
+    operation_rows: Vec<Vec<String>>,
+    // The rows of the streaming stats.
+    streaming_rows: Vec<Vec<String>>,
+    // The rows of the statistics over the iterations.
+    iteration_rows: Vec<Vec<String>>,
+}
+
+impl ReportWriter {
+    /// Creates a writer to the file at `path`, or to the stdout if `path` is
+    /// `None`.
+    pub fn new(format: OutputFormat, path: Option<&Path>) -> anyhow::Result<Self> {
+        let (out, to_stdout): (Box<dyn Write>, _) = match path {
+            Some(path) => {
+                let f = File::create(path)
+                    .with_context(|| format!("Cannot create output file {}", path.display()))?;
+                (Box::new(BufWriter::new(f)), false)
+            }
+            None => (Box::new(io::stdout()), true),
+        };
+
+        Ok(Self {
+            format,
+            out,
+            to_stdout,
+            latency_rows: Vec::new(),
+            operation_rows: Vec::new(),
+            streaming_rows: Vec::new(),
+            iteration_rows: Vec::new(),
+        })
+    }
+
+    /// Prints an informational message. It goes to the stdout only when the
+    /// reports are written to the stdout in CSV format. Otherwise, it goes to the
+    /// stderr so that it will not mix with the structured output.
+    pub fn info(&self, message: &str) {
+        if self.to_stdout && self.format == OutputFormat::Csv {
+            println!("{message}");
+        } else {
+            eprintln!("{message}");
+        }
+    }
+
+    pub fn begin_trace(&mut self, config: &Config) -> anyhow::Result<()> {
+        self.info(&format!("{config:?}"));
+        self.info("");
+
+        let has_eviction_counters = config.is_eviction_listener_enabled();
+        match self.format {
+            OutputFormat::Csv => {
+                writeln!(self.out, "{}", Report::cvs_header(has_eviction_counters))?
+            }
+            OutputFormat::Json => (),
+            OutputFormat::Markdown => {
+                match &config.label {
+                    Some(label) => writeln!(self.out, "### {label}: {}", config.trace_file)?,
+                    None => writeln!(self.out, "### {}", config.trace_file)?,
+                }
+                writeln!(self.out)?;
+                write_markdown_header(&mut self.out, &Report::columns(has_eviction_counters))?;
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    pub fn write_report(&mut self, config: &Config, report: &Report) -> anyhow::Result<()> {
+        match self.format {
+            OutputFormat::Csv => {
+                writeln!(self.out, "{}", report.to_csv_record())?;
+                self.operation_rows.extend(report.to_operation_fields());
+                self.latency_rows.extend(report.to_latency_fields());
+                self.streaming_rows.extend(report.to_streaming_fields());
+                self.iteration_rows.extend(report.to_iteration_fields());
+            }
+            OutputFormat::Json => {
+                let record = RunRecord {
+                    label: config.label.clone(),
+                    trace: config.trace_file.to_string(),
+                    config: serde_json::to_value(config)?,
+                    report: ReportRecord::from(report),
+                };
+                serde_json::to_writer(&mut self.out, &record)?;
+                writeln!(self.out)?;
+            }
+            OutputFormat::Markdown => {
+                write_markdown_row(&mut self.out, &report.to_fields())?;
+                self.operation_rows.extend(report.to_operation_fields());
+                self.latency_rows.extend(report.to_latency_fields());
+                self.streaming_rows.extend(report.to_streaming_fields());
+                self.iteration_rows.extend(report.to_iteration_fields());
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    pub fn end_trace(&mut self) -> anyhow::Result<()> {
+        if self.format == OutputFormat::Markdown {
+            writeln!(self.out)?;
+        }
+        let tables = [
+            (
+                Report::operation_columns(),
+                mem::take(&mut self.operation_rows),
+            ),
+            (Report::latency_columns(), mem::take(&mut self.latency_rows)),
+            (
+                Report::streaming_columns(),
+                mem::take(&mut self.streaming_rows),
+            ),
+            (
+                Report::iteration_columns(),
+                mem::take(&mut self.iteration_rows),
+            ),
+        ];
+        for (columns, rows) in tables {
+            self.write_table(&columns, &rows)?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Writes the rows as a separate table after the main one. In CSV format, the
+    /// table is preceded by an empty line and its own header.
+    fn write_table(&mut self, columns: &[&str], rows: &[Vec<String>]) -> io::Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        match self.format {
+            OutputFormat::Csv => {
+                writeln!(self.out)?;
+                writeln!(self.out, "{}", columns.join(", "))?;
+                for row in rows {
+                    writeln!(self.out, "{}", row.join(", "))?;
+                }
+            }
+            OutputFormat::Json => (),
+            OutputFormat::Markdown => {
+                write_markdown_header(&mut self.out, columns)?;
+                for row in rows {
+                    write_markdown_row(&mut self.out, row)?;
+                }
+                writeln!(self.out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_markdown_header(out: &mut impl Write, columns: &[&str]) -> io::Result<()> {
+    writeln!(out, "| {} |", columns.join(" | "))?;
+    let separators = columns
+        .iter()
+        .enumerate()
+        // Left-align the first column (cache name) and right-align the numbers.
+        .map(|(i, _)| if i == 0 { ":---" } else { "---:" })
+        .collect::<Vec<_>>();
+    writeln!(out, "|{}|", separators.join("|"))
+}
+
+fn write_markdown_row(out: &mut impl Write, fields: &[String]) -> io::Result<()> {
+    writeln!(out, "| {} |", fields.join(" | "))
+}
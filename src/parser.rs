@@ -1,14 +1,36 @@
+use crate::trace_file::TraceFileGroup;
+
 pub trait TraceParser<T> {
     fn parse(&mut self, line: &str, line_number: usize) -> anyhow::Result<Option<T>>;
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TraceEntry {
     range: std::ops::Range<usize>,
     line_number: usize,
+    weight: Option<u32>,
 }
 
 impl TraceEntry {
+    pub(crate) fn new(range: std::ops::Range<usize>, line_number: usize) -> Self {
+        Self {
+            range,
+            line_number,
+            weight: None,
+        }
+    }
+
+    /// Converts the entry into a single key, the start of the range, weighted by
+    /// the length of the range unless the entry already has a weight.
+    pub(crate) fn into_weighted(self) -> Self {
+        let len = self.range.len();
+        Self {
+            range: self.range.start..(self.range.start + 1),
+            line_number: self.line_number,
+            weight: self.weight.or(Some(len.clamp(1, u32::MAX as usize) as u32)),
+        }
+    }
+
     pub fn range(&self) -> std::ops::Range<usize> {
         self.range.clone()
     }
@@ -16,11 +38,59 @@ impl TraceEntry {
     pub fn line_number(&self) -> usize {
         self.line_number
     }
+
+    /// Returns the keys of the entry with their requests.
+    pub(crate) fn requests(&self) -> impl Iterator<Item = (usize, Request)> {
+        let weight = self.weight;
+        self.range()
+            .zip(self.line_number..)
+            .map(move |(key, id)| (key, Request { id, weight }))
+    }
+}
+
+/// A request for a key of a trace entry.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Request {
+    /// The id of the request, unique in a pass of the trace. It chooses the weight
+    /// of the value in the size-aware mode, unless the entry has a weight.
+    pub(crate) id: usize,
+    pub(crate) weight: Option<u32>,
 }
 
 // Arc traces contains a 2+ numbers per line, the first two being start and len, meaning a range `start..start+len`
 // LIRS/LIRS2 traces contains a single key per line
-pub struct GenericTraceParser;
+//
+// With the LIRS format, only the first integer in a line is used as the key, so
+// custom traces can have extra columns (e.g. timestamps) after the key.
+//
+// With `weight_column`, the weights of the values are read from that (1-based)
+// column of the lines that have it.
+pub struct GenericTraceParser {
+    format: Option<TraceFileGroup>,
+    weight_column: Option<usize>,
+}
+
+impl GenericTraceParser {
+    pub fn new(format: Option<TraceFileGroup>, weight_column: Option<usize>) -> Self {
+        Self {
+            format,
+            weight_column,
+        }
+    }
+
+    fn parse_weight(&self, line: &str) -> anyhow::Result<Option<u32>> {
+        let Some(column) = self.weight_column else {
+            return Ok(None);
+        };
+        let Some(token) = line.split_ascii_whitespace().nth(column - 1) else {
+            return Ok(None);
+        };
+        let weight = token.parse::<u32>().map_err(|_| {
+            anyhow::anyhow!(r#"Cannot parse "{token}" in column {column} as a weight"#)
+        })?;
+        Ok(Some(weight))
+    }
+}
 
 impl TraceParser<TraceEntry> for GenericTraceParser {
     fn parse(&mut self, line: &str, line_number: usize) -> anyhow::Result<Option<TraceEntry>> {
@@ -28,22 +98,21 @@ impl TraceParser<TraceEntry> for GenericTraceParser {
             // LIRS/LIRS2 traces contains `*` lines which are NOOPs
             return Ok(None);
         }
-        let mut tokens = line.split(' ');
+        let mut tokens = line.split_ascii_whitespace();
         let start = if let Some(token) = tokens.next() {
             token.parse::<usize>()?
         } else {
             anyhow::bail!("Expected at least one integer in the line: {}", line);
         };
-        let len = if let Some(token) = tokens.next() {
-            token.parse::<usize>()?
-        } else {
+        let len = match tokens.next() {
+            // LIRS/LIRS2 traces may have extra columns after the key. Ignore them.
+            Some(token) if self.format != Some(TraceFileGroup::Lirs) => token.parse::<usize>()?,
             // single integer per line format
-            1
+            _ => 1,
         };
 
-        Ok(Some(TraceEntry {
-            range: start..(start + len),
-            line_number,
-        }))
+        let mut entry = TraceEntry::new(start..(start + len), line_number);
+        entry.weight = self.parse_weight(line)?;
+        Ok(Some(entry))
     }
 }
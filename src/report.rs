@@ -1,30 +1,55 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
-use crate::eviction_counters::EvictionCounters;
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+
+use crate::{eviction_counters::EvictionCounters, op_mix::Operation};
 
 pub struct ReportBuilder {
     name: String,
+    version: Option<String>,
     capacity: u64,
     num_workers: Option<u16>,
+    latencies: bool,
 }
 
 impl ReportBuilder {
     pub fn new(name: &str, capacity: u64, num_workers: Option<u16>) -> Self {
         Self {
             name: name.to_string(),
+            version: None,
             capacity,
             num_workers,
+            latencies: false,
         }
     }
 
+    /// Sets the version of the cache, e.g. `0.12` for Moka.
+    pub fn set_version(&mut self, version: &str) {
+        self.version = Some(version.to_string());
+    }
+
+    /// Makes the built reports record the latency of each command.
+    pub fn enable_latencies(&mut self, v: bool) {
+        self.latencies = v;
+    }
+
     pub fn build(&self) -> Report {
-        Report::new(&self.name, self.capacity, self.num_workers)
+        let mut report = Report::new(&self.name, self.capacity, self.num_workers);
+        report.version.clone_from(&self.version);
+        if self.latencies {
+            report.enable_latencies();
+        }
+        report
     }
 }
 
 #[derive(Clone, Default)]
 pub struct Report {
     pub name: String,
+    /// The version of the cache. It is not a part of the name, so that the runs
+    /// of different versions can be compared by `mokabench compare`.
+    pub version: Option<String>,
     pub capacity: u64,
     pub num_workers: Option<u16>,
     pub has_eviction_counts: bool,
@@ -36,6 +61,13 @@ pub struct Report {
     pub eviction_count: u64,
     pub expiration_count: u64,
     pub duration: Option<Duration>,
+    /// The number of commands of each operation type, indexed by `Operation`.
+    op_counts: [u64; Operation::ALL.len()],
+    pub latencies: Option<Latencies>,
+    /// The statistics over the iterations when the run was repeated.
+    pub iterations: Option<IterationStats>,
+    /// The stats of the producer and the channel in the streaming mode.
+    pub streaming: Option<StreamingStats>,
 }
 
 impl Report {
@@ -48,6 +80,70 @@ impl Report {
         }
     }
 
+    pub fn enable_latencies(&mut self) {
+        self.latencies = Some(Latencies::default());
+    }
+
+    pub(crate) fn record_latency(&mut self, op: Operation, latency: Duration) {
+        if let Some(latencies) = &mut self.latencies {
+            latencies.record(op, latency);
+        }
+    }
+
+    pub(crate) fn count_operation(&mut self, op: Operation) {
+        self.op_counts[op as usize] += 1;
+    }
+
+    /// Returns the number of commands of each operation type that was run.
+    pub fn operation_counts(&self) -> impl Iterator<Item = (Operation, u64)> + '_ {
+        Operation::ALL
+            .into_iter()
+            .zip(self.op_counts)
+            .filter(|(_, count)| *count > 0)
+    }
+
+    /// Returns the total number of operations: the reads and inserts on the
+    /// cache, and the invalidation and iteration commands. Unlike the number of
+    /// commands, it counts each key of a ranged trace entry.
+    pub fn total_ops(&self) -> u64 {
+        let commands = |op: Operation| self.op_counts[op as usize];
+        self.read_count
+            + self.insert_count
+            + commands(Operation::Invalidate)
+            + commands(Operation::InvalidateAll)
+            + commands(Operation::InvalidateEntriesIf)
+            + commands(Operation::Iterate)
+    }
+
+    /// Returns the total operations per second.
+    pub fn ops_per_sec(&self) -> Option<f64> {
+        self.per_sec(self.total_ops())
+    }
+
+    /// Returns the average time per operation in nanoseconds.
+    pub fn nanos_per_op(&self) -> Option<f64> {
+        let ops = self.total_ops();
+        self.duration
+            .filter(|_| ops > 0)
+            .map(|d| d.as_nanos() as f64 / ops as f64)
+    }
+
+    /// Returns the count divided by the duration in seconds.
+    pub(crate) fn per_sec(&self, count: u64) -> Option<f64> {
+        self.duration
+            .map(|d| d.as_secs_f64())
+            .filter(|secs| *secs > 0.0)
+            .map(|secs| count as f64 / secs)
+    }
+
+    /// Returns the name with the version, if any, e.g. "Moka Sync Cache (0.12)".
+    pub fn display_name(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{} ({version})", self.name),
+            None => self.name.clone(),
+        }
+    }
+
     pub fn hit_ratio(&self) -> f64 {
         (self.hit_count as f64) / (self.read_count as f64)
     }
@@ -56,11 +152,56 @@ impl Report {
         self.insert_count += other.insert_count;
         self.read_count += other.read_count;
         self.hit_count += other.hit_count;
+        for (count, other_count) in self.op_counts.iter_mut().zip(other.op_counts) {
+            *count += other_count;
+        }
         if self.has_eviction_counts {
             self.invalidation_count += other.invalidation_count;
             self.eviction_count += other.eviction_count;
             self.expiration_count += other.expiration_count;
         }
+        if let (Some(latencies), Some(other_latencies)) = (&mut self.latencies, &other.latencies) {
+            latencies.merge(other_latencies);
+        }
+    }
+
+    /// Makes a report from the reports of the measured iterations of a run. The
+    /// counts and latencies are merged and the duration is the total, so the
+    /// throughput and hit ratio are those of all iterations together.
+    pub(crate) fn from_iterations(reports: Vec<Report>, warmup: usize, max_cv: f64) -> Self {
+        let durations = reports
+            .iter()
+            .filter_map(|r| r.duration.map(|d| d.as_secs_f64()))
+            .collect::<Vec<_>>();
+        let throughputs = reports
+            .iter()
+            .filter_map(|r| r.ops_per_sec())
+            .collect::<Vec<_>>();
+        let is_repeated = reports.len() > 1 && durations.len() == reports.len();
+
+        let mut reports = reports.into_iter();
+        let mut report = reports.next().expect("No iterations");
+        for other in reports {
+            report.merge(&other);
+            report.duration = report.duration.zip(other.duration).map(|(d1, d2)| d1 + d2);
+            if let (Some(stats), Some(other_stats)) = (&mut report.streaming, &other.streaming) {
+                stats.producer_stall += other_stats.producer_stall;
+                stats.client_wait += other_stats.client_wait;
+            }
+        }
+
+        if is_repeated {
+            let duration = Summary::new(&durations);
+            let throughput = Summary::new(&throughputs);
+            report.iterations = Some(IterationStats {
+                iterations: durations.len(),
+                warmup,
+                high_variance: duration.cv() > max_cv || throughput.cv() > max_cv,
+                duration_secs: duration,
+                throughput,
+            });
+        }
+        report
     }
 
     pub(crate) fn add_eviction_counts(&mut self, eviction_counters: &EvictionCounters) {
@@ -73,14 +214,49 @@ impl Report {
     // Formatting (CSV)
 
     pub fn cvs_header(has_eviction_counters: bool) -> String {
+        Self::columns(has_eviction_counters).join(", ")
+    }
+
+    pub fn to_csv_record(&self) -> String {
+        self.to_fields().join(", ")
+    }
+
+    // Formatting (table columns)
+
+    pub fn columns(has_eviction_counters: bool) -> Vec<&'static str> {
         if has_eviction_counters {
-            "Cache, Max Capacity, Clients, Inserts, Reads, Hit Ratio, Invalidates, Evicted by Size, Expired, Duration Secs".into()
+            vec![
+                "Cache",
+                "Max Capacity",
+                "Clients",
+                "Inserts",
+                "Reads",
+                "Hit Ratio",
+                "Invalidates",
+                "Evicted by Size",
+                "Expired",
+                "Duration Secs",
+                "Ops",
+                "Ops/sec",
+                "ns/op",
+            ]
         } else {
-            "Cache, Max Capacity, Clients, Inserts, Reads, Hit Ratio, Duration Secs".into()
+            vec![
+                "Cache",
+                "Max Capacity",
+                "Clients",
+                "Inserts",
+                "Reads",
+                "Hit Ratio",
+                "Duration Secs",
+                "Ops",
+                "Ops/sec",
+                "ns/op",
+            ]
         }
     }
 
-    pub fn to_csv_record(&self) -> String {
+    pub fn to_fields(&self) -> Vec<String> {
         let num_workers = if let Some(n) = self.num_workers {
             n.to_string()
         } else {
@@ -93,31 +269,349 @@ impl Report {
             "-".to_string()
         };
 
+        let mut fields = vec![
+            self.display_name(),
+            self.capacity.to_string(),
+            num_workers,
+            self.insert_count.to_string(),
+            self.read_count.to_string(),
+            format!("{:.3}", self.hit_ratio() * 100.0),
+        ];
         if self.has_eviction_counts {
-            format!(
-                "{}, {}, {}, {}, {}, {:.3}, {}, {}, {}, {}",
-                self.name,
-                self.capacity,
-                num_workers,
-                self.insert_count,
-                self.read_count,
-                self.hit_ratio() * 100.0,
-                self.invalidation_count,
-                self.eviction_count,
-                self.expiration_count,
-                duration
-            )
+            fields.push(self.invalidation_count.to_string());
+            fields.push(self.eviction_count.to_string());
+            fields.push(self.expiration_count.to_string());
+        }
+        fields.push(duration);
+        fields.push(self.total_ops().to_string());
+        fields.push(opt_field(self.ops_per_sec(), 0));
+        fields.push(opt_field(self.nanos_per_op(), 1));
+        fields
+    }
+
+    pub fn operation_columns() -> Vec<&'static str> {
+        vec![
+            "Cache",
+            "Max Capacity",
+            "Clients",
+            "Operation",
+            "Count",
+            "Ops/sec",
+        ]
+    }
+
+    /// Returns the fields for each operation type with the number of commands and
+    /// their throughput. Returns an empty `Vec` if only one operation type was run,
+    /// as the throughput is the same as the whole run.
+    pub fn to_operation_fields(&self) -> Vec<Vec<String>> {
+        if self.operation_counts().count() < 2 {
+            return Vec::new();
+        }
+        let num_workers = self
+            .num_workers
+            .map_or_else(|| "-".to_string(), |n| n.to_string());
+        self.operation_counts()
+            .map(|(op, count)| {
+                vec![
+                    self.display_name(),
+                    self.capacity.to_string(),
+                    num_workers.clone(),
+                    op.name().to_string(),
+                    count.to_string(),
+                    opt_field(self.per_sec(count), 0),
+                ]
+            })
+            .collect()
+    }
+
+    pub fn latency_columns() -> Vec<&'static str> {
+        vec![
+            "Cache",
+            "Max Capacity",
+            "Clients",
+            "Operation",
+            "Count",
+            "p50 us",
+            "p90 us",
+            "p99 us",
+            "p99.9 us",
+            "Max us",
+        ]
+    }
+
+    /// Returns the fields for each operation type with the latency percentiles in
+    /// microseconds. Returns an empty `Vec` if latencies were not recorded.
+    pub fn to_latency_fields(&self) -> Vec<Vec<String>> {
+        let Some(latencies) = &self.latencies else {
+            return Vec::new();
+        };
+        let us = |ns: u64| format!("{:.3}", ns as f64 / 1_000.0);
+        let num_workers = self
+            .num_workers
+            .map_or_else(|| "-".to_string(), |n| n.to_string());
+        latencies
+            .summaries()
+            .map(|s| {
+                vec![
+                    self.display_name(),
+                    self.capacity.to_string(),
+                    num_workers.clone(),
+                    s.operation.name().to_string(),
+                    s.count.to_string(),
+                    us(s.p50),
+                    us(s.p90),
+                    us(s.p99),
+                    us(s.p999),
+                    us(s.max),
+                ]
+            })
+            .collect()
+    }
+
+    pub fn streaming_columns() -> Vec<&'static str> {
+        vec![
+            "Cache",
+            "Max Capacity",
+            "Clients",
+            "Channel Capacity",
+            "Producer Stall Secs",
+            "Client Wait Secs",
+        ]
+    }
+
+    pub fn to_streaming_fields(&self) -> Option<Vec<String>> {
+        let stats = self.streaming.as_ref()?;
+        let num_workers = self
+            .num_workers
+            .map_or_else(|| "-".to_string(), |n| n.to_string());
+        Some(vec![
+            self.display_name(),
+            self.capacity.to_string(),
+            num_workers,
+            stats.channel_capacity.to_string(),
+            format!("{:.3}", stats.producer_stall.as_secs_f64()),
+            format!("{:.3}", stats.client_wait.as_secs_f64()),
+        ])
+    }
+
+    pub fn iteration_columns() -> Vec<&'static str> {
+        vec![
+            "Cache",
+            "Max Capacity",
+            "Clients",
+            "Metric",
+            "Iterations",
+            "Mean",
+            "Median",
+            "Stddev",
+            "Min",
+            "Max",
+            "95% CI",
+            "CV %",
+        ]
+    }
+
+    /// Returns the fields for each metric with the statistics over the iterations.
+    /// Returns an empty `Vec` if the run was not repeated.
+    pub fn to_iteration_fields(&self) -> Vec<Vec<String>> {
+        let Some(stats) = &self.iterations else {
+            return Vec::new();
+        };
+        let num_workers = self
+            .num_workers
+            .map_or_else(|| "-".to_string(), |n| n.to_string());
+        [
+            ("Duration Secs", &stats.duration_secs, 3),
+            ("Ops/sec", &stats.throughput, 0),
+        ]
+        .into_iter()
+        .map(|(metric, s, p)| {
+            let mut cv = format!("{:.1}", s.cv());
+            if stats.high_variance {
+                cv.push_str(" (high)");
+            }
+            vec![
+                self.display_name(),
+                self.capacity.to_string(),
+                num_workers.clone(),
+                metric.to_string(),
+                stats.iterations.to_string(),
+                format!("{:.p$}", s.mean),
+                format!("{:.p$}", s.median),
+                format!("{:.p$}", s.stddev),
+                format!("{:.p$}", s.min),
+                format!("{:.p$}", s.max),
+                format!("{:.p$} - {:.p$}", s.ci_low, s.ci_high),
+                cv,
+            ]
+        })
+        .collect()
+    }
+}
+
+fn opt_field(v: Option<f64>, precision: usize) -> String {
+    match v {
+        Some(v) => format!("{v:.precision$}"),
+        None => "-".to_string(),
+    }
+}
+
+/// The time the harness was blocked in the streaming mode. A long producer stall
+/// means the caches were the bottleneck, and a long client wait means the
+/// producer (reading and parsing the trace) was the bottleneck.
+#[derive(Clone, Debug)]
+pub struct StreamingStats {
+    /// The capacity of the channel in chunks of commands.
+    pub channel_capacity: usize,
+    /// The total time the producer was blocked on a full channel.
+    pub producer_stall: Duration,
+    /// The total time the clients were blocked on an empty channel, summed over
+    /// the clients.
+    pub client_wait: Duration,
+}
+
+/// The statistics of the measured iterations of a run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IterationStats {
+    pub iterations: usize,
+    pub warmup: usize,
+    pub duration_secs: Summary,
+    /// Operations per second.
+    pub throughput: Summary,
+    /// `true` if the coefficient of variation of the duration or throughput
+    /// exceeds the threshold.
+    pub high_variance: bool,
+}
+
+/// The summary statistics of a sample. `ci_low` and `ci_high` are the bounds of
+/// the 95% confidence interval of the mean.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Summary {
+    pub mean: f64,
+    pub median: f64,
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+impl Summary {
+    pub fn new(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+
+        let n = values.len();
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let median = if n % 2 == 0 {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
         } else {
-            format!(
-                "{}, {}, {}, {}, {}, {:.3}, {}",
-                self.name,
-                self.capacity,
-                num_workers,
-                self.insert_count,
-                self.read_count,
-                self.hit_ratio() * 100.0,
-                duration
-            )
+            sorted[n / 2]
+        };
+        // The sample standard deviation.
+        let stddev = if n > 1 {
+            let sum_sq = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>();
+            (sum_sq / (n - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let half_width = t_critical_95(n - 1) * stddev / (n as f64).sqrt();
+
+        Self {
+            mean,
+            median,
+            stddev,
+            min: sorted[0],
+            max: sorted[n - 1],
+            ci_low: mean - half_width,
+            ci_high: mean + half_width,
         }
     }
+
+    /// The coefficient of variation in percent.
+    pub fn cv(&self) -> f64 {
+        if self.mean == 0.0 {
+            0.0
+        } else {
+            self.stddev / self.mean * 100.0
+        }
+    }
+}
+
+/// Returns the critical value of Student's t-distribution for the two-sided 95%
+/// confidence interval with the degrees of freedom.
+fn t_critical_95(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match degrees_of_freedom {
+        0 => 0.0,
+        n if n <= TABLE.len() => TABLE[n - 1],
+        // Approximated by the normal distribution.
+        _ => 1.960,
+    }
+}
+
+// Latencies longer than this (one minute) are recorded as this value.
+const MAX_LATENCY_NANOS: u64 = 60 * 1_000_000_000;
+
+/// Latency histograms of the commands, per operation type. Values are recorded in
+/// nanoseconds.
+///
+/// For ARC traces, a command covers all blocks in the range of a trace entry, so
+/// its latency is the total latency of the blocks.
+#[derive(Clone, Default)]
+pub struct Latencies {
+    histograms: BTreeMap<Operation, Histogram<u64>>,
+}
+
+impl Latencies {
+    fn record(&mut self, op: Operation, latency: Duration) {
+        self.histograms
+            .entry(op)
+            .or_insert_with(|| {
+                Histogram::new_with_max(MAX_LATENCY_NANOS, 3).expect("Failed to create a histogram")
+            })
+            .saturating_record(latency.as_nanos() as u64);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        for (op, histogram) in &other.histograms {
+            match self.histograms.get_mut(op) {
+                Some(h) => h.add(histogram).expect("Failed to merge histograms"),
+                None => {
+                    self.histograms.insert(*op, histogram.clone());
+                }
+            }
+        }
+    }
+
+    pub fn summaries(&self) -> impl Iterator<Item = LatencySummary> + '_ {
+        self.histograms.iter().map(|(op, h)| LatencySummary {
+            operation: *op,
+            count: h.len(),
+            p50: h.value_at_quantile(0.5),
+            p90: h.value_at_quantile(0.9),
+            p99: h.value_at_quantile(0.99),
+            p999: h.value_at_quantile(0.999),
+            max: h.max(),
+        })
+    }
+}
+
+/// Latency percentiles of an operation type, in nanoseconds.
+#[derive(Clone, Debug)]
+pub struct LatencySummary {
+    pub operation: Operation,
+    pub count: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
 }
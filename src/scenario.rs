@@ -0,0 +1,218 @@
+//! Scenario files in TOML format. A scenario file describes a benchmark matrix as
+//! a list of scenarios, each of which has the traces (or generators), capacities,
+//! client counts, caches and workload options to run. For example:
+//!
+//! ```toml
+//! [[scenario]]
+//! label = "arc-traces"
+//! traces = ["s3", "ds1"]
+//! clients = [16, 32]
+//! caches = ["moka-sync", "moka-async"]
+//!
+//! [[scenario]]
+//! label = "zipf-with-ttl"
+//! generators = ["zipf"]
+//! skew = [0.8, 1.0]
+//! key-space = 100000
+//! capacities = [10000]
+//! mix = "get=90,invalidate=10"
+//! ttl = 3
+//! ```
+
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{
+    config::{CacheKind, Config},
+    generator::{self, Workload},
+    op_mix::{MixMode, OpMix},
+    trace_file::{TraceFile, TraceFileGroup},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioFile {
+    #[serde(rename = "scenario")]
+    pub scenarios: Vec<Scenario>,
+}
+
+impl ScenarioFile {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read scenario file {}", path.display()))?;
+        let file: Self = toml::from_str(&text)
+            .with_context(|| format!("Cannot parse scenario file {}", path.display()))?;
+        if file.scenarios.is_empty() {
+            anyhow::bail!("The scenario file {} has no scenario", path.display());
+        }
+        Ok(file)
+    }
+}
+
+/// A scenario. The options not given here are taken from the command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Scenario {
+    /// The label to identify the reports of this scenario in the output.
+    pub label: String,
+    /// The names or paths of the trace files.
+    pub traces: Vec<String>,
+    pub trace_format: Option<String>,
+    /// The synthetic workload generators, configured by the fields below.
+    pub generators: Vec<String>,
+    pub skew: Vec<f64>,
+    pub hot_key_fraction: Option<f64>,
+    pub hot_op_fraction: Option<f64>,
+    pub key_space: Option<usize>,
+    pub num_ops: Option<usize>,
+    pub seed: Option<u64>,
+    pub capacities: Option<Vec<usize>>,
+    pub clients: Option<Vec<u16>>,
+    pub caches: Option<Vec<String>>,
+    /// The number of segments of Moka's `SegmentedCache`.
+    pub segments: Option<usize>,
+    /// The operation mix, e.g. `"get=80,insert=20"`.
+    pub mix: Option<String>,
+    /// `"periodic"` or `"random"`.
+    pub mix_mode: Option<String>,
+    pub ttl: Option<u64>,
+    pub tti: Option<u64>,
+    pub repeat: Option<u16>,
+    /// The time limit of each run, e.g. `"30s"`.
+    pub duration: Option<String>,
+    pub max_ops: Option<u64>,
+    /// The number of measured and warm-up iterations of each run.
+    pub iterations: Option<usize>,
+    pub warmup: Option<usize>,
+}
+
+impl Scenario {
+    /// Returns the trace files of the scenario, and the config made by applying the
+    /// scenario to `base`.
+    pub fn to_config(&self, base: &Config) -> anyhow::Result<(Vec<TraceFile>, Config)> {
+        self.to_config_inner(base)
+            .with_context(|| format!(r#"Invalid scenario "{}""#, self.label))
+    }
+
+    fn to_config_inner(&self, base: &Config) -> anyhow::Result<(Vec<TraceFile>, Config)> {
+        if self.label.is_empty() {
+            anyhow::bail!("The label is required");
+        }
+
+        let trace_files = self.trace_files()?;
+        if trace_files.is_empty() {
+            anyhow::bail!("Either traces or generators is required");
+        }
+
+        let mut config = base.clone();
+        config.trace_file = trace_files[0].clone();
+        config.set_label(Some(self.label.clone()));
+
+        if self.capacities.is_some() {
+            config.set_capacities(self.capacities.clone());
+        }
+        if config.capacities.is_none() && config.mrc.is_none() {
+            if let Some(custom) = trace_files.iter().find(|f| f.is_custom()) {
+                anyhow::bail!(
+                    "capacities are required for the custom trace file {}",
+                    custom.path().display()
+                );
+            }
+        }
+        if self.clients.is_some() {
+            config.num_clients = self.clients.clone();
+        }
+        if let Some(caches) = &self.caches {
+            config.set_caches(Some(CacheKind::parse_list(
+                caches.iter().map(String::as_str),
+            )?));
+        }
+        if let Some(segments) = self.segments {
+            config.set_num_segments(segments);
+        }
+        if let Some(mix) = &self.mix {
+            let mode = match self.mix_mode.as_deref() {
+                None | Some("periodic") => MixMode::Periodic,
+                Some("random") => MixMode::Random {
+                    seed: self.seed.unwrap_or_default(),
+                },
+                Some(v) => anyhow::bail!(
+                    r#"mix-mode must be "periodic" or "random", but got "{}""#,
+                    v
+                ),
+            };
+            config.set_op_mix(Some(OpMix::parse(mix.split(','), mode)?));
+        }
+        if let Some(ttl) = self.ttl {
+            config.ttl = Some(std::time::Duration::from_secs(ttl));
+        }
+        if let Some(tti) = self.tti {
+            config.tti = Some(std::time::Duration::from_secs(tti));
+        }
+        if self.repeat.is_some() {
+            config.repeat = self.repeat;
+        }
+        if let Some(duration) = &self.duration {
+            let time_limit = humantime::parse_duration(duration)
+                .with_context(|| format!(r#"Cannot parse duration "{duration}""#))?;
+            config.set_time_limit(Some(time_limit));
+        }
+        if self.max_ops.is_some() {
+            config.set_max_ops(self.max_ops);
+        }
+        if config.repeat.is_some() && (config.time_limit.is_some() || config.max_ops.is_some()) {
+            anyhow::bail!("repeat cannot be used with duration or max-ops");
+        }
+        if self.iterations == Some(0) {
+            anyhow::bail!("iterations must be a positive integer");
+        }
+        config.set_iterations(
+            self.iterations.unwrap_or(base.iterations),
+            self.warmup.unwrap_or(base.warmup_iterations),
+            base.max_cv,
+        );
+
+        Ok((trace_files, config))
+    }
+
+    fn trace_files(&self) -> anyhow::Result<Vec<TraceFile>> {
+        let mut trace_files = self
+            .traces
+            .iter()
+            .map(|t| TraceFile::try_from(t.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(v) = &self.trace_format {
+            let format = TraceFileGroup::try_from(v.as_str())?;
+            for trace_file in &mut trace_files {
+                if let TraceFile::Custom(custom) = trace_file {
+                    custom.format = Some(format);
+                }
+            }
+        }
+
+        let skews = if self.skew.is_empty() {
+            vec![generator::DEFAULT_SKEW]
+        } else {
+            self.skew.clone()
+        };
+        for name in &self.generators {
+            let workloads = Workload::parse_each_skew(
+                name,
+                &skews,
+                self.hot_key_fraction
+                    .unwrap_or(generator::DEFAULT_HOT_KEY_FRACTION),
+                self.hot_op_fraction
+                    .unwrap_or(generator::DEFAULT_HOT_OP_FRACTION),
+                self.key_space.unwrap_or(generator::DEFAULT_KEY_SPACE),
+                self.num_ops.unwrap_or(generator::DEFAULT_NUM_OPS),
+                self.seed.unwrap_or_default(),
+            )?;
+            trace_files.extend(workloads.into_iter().map(TraceFile::Synthetic));
+        }
+
+        Ok(trace_files)
+    }
+}
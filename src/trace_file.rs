@@ -1,15 +1,62 @@
-use std::path::{Path, PathBuf};
+use std::{
+    env,
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::generator::Workload;
 
 pub const DATASET_DIR: &str = "./cache-trace";
 
-#[derive(Clone, Copy, Debug)]
+/// The environment variable to override [`DATASET_DIR`].
+pub const DATASET_DIR_ENV: &str = "MOKABENCH_DATASET_DIR";
+
+/// Returns the directory containing the built-in trace files. It is
+/// [`DATASET_DIR`] unless overridden by the [`DATASET_DIR_ENV`] environment
+/// variable.
+pub fn dataset_dir() -> PathBuf {
+    env::var_os(DATASET_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DATASET_DIR))
+}
+
+/// The group of a trace file. It also tells the format of the lines in the file:
+///
+/// - `Arc`: Two or more integers per line, the first two being the start and
+///   length of a range of keys.
+/// - `Lirs`: A single key per line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TraceFileGroup {
     Arc,
     Lirs,
 }
 
+impl TryFrom<&str> for TraceFileGroup {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim().to_lowercase().as_str() {
+            "arc" => Ok(Self::Arc),
+            "lirs" => Ok(Self::Lirs),
+            _ => Err(anyhow::anyhow!(
+                r#"Unknown trace format "{}". It must be "arc" or "lirs""#,
+                value
+            )),
+        }
+    }
+}
+
 impl TraceFileGroup {
-    pub fn new(file: TraceFile) -> Self {
+    pub fn new(file: &TraceFile) -> Self {
+        if let TraceFile::Custom(custom) = file {
+            return custom.format.unwrap_or(Self::Arc);
+        }
+
         if matches!(
             file,
             TraceFile::Loop
@@ -34,7 +81,15 @@ impl TraceFileGroup {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// A trace file outside the dataset directory, given by its path.
+#[derive(Clone, Debug)]
+pub struct CustomTraceFile {
+    pub path: PathBuf,
+    /// The format of the lines. If `None`, it will be detected from each line.
+    pub format: Option<TraceFileGroup>,
+}
+
+#[derive(Clone, Debug)]
 pub enum TraceFile {
     // From ARC paper: "ARC: A Self-Tuning, Low Overhead Replacement Cache"
     // Traces and paper in the author page: https://researcher.watson.ibm.com/researcher/view_person_subpage.php?id=4700
@@ -72,6 +127,12 @@ pub enum TraceFile {
     Sprite,
     // Lirs2
     ZigZag,
+
+    // A trace file given by its path.
+    Custom(CustomTraceFile),
+
+    // Not a file. The trace is generated by a synthetic workload generator.
+    Synthetic(Workload),
 }
 
 impl TryFrom<&str> for TraceFile {
@@ -110,15 +171,106 @@ impl TryFrom<&str> for TraceFile {
             "2-pools" => Ok(Self::ZigZag),
             "sprite" => Ok(Self::Sprite),
             "zigzag" => Ok(Self::ZigZag),
-            _ => Err(anyhow::anyhow!(r#"Unknown trace file "{}""#, value)),
+            _ => {
+                // Not a built-in name. Treat it as a path if it looks like one.
+                let path = Path::new(value.trim());
+                if path.components().count() > 1
+                    || path.exists()
+                    || append_extension(path, "zst").exists()
+                {
+                    Ok(Self::custom(path, None))
+                } else {
+                    Err(anyhow::anyhow!(r#"Unknown trace file "{}""#, value))
+                }
+            }
         }
     }
 }
 
+impl fmt::Display for TraceFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::ConCat => "concat",
+            Self::Ds1 => "ds1",
+            Self::MergeP => "merge-p",
+            Self::MergeS => "merge-s",
+            Self::Oltp => "oltp",
+            Self::P1 => "p1",
+            Self::P2 => "p2",
+            Self::P3 => "p3",
+            Self::P4 => "p4",
+            Self::P5 => "p5",
+            Self::P6 => "p6",
+            Self::P7 => "p7",
+            Self::P8 => "p8",
+            Self::P9 => "p9",
+            Self::P10 => "p10",
+            Self::P11 => "p11",
+            Self::P12 => "p12",
+            Self::P13 => "p13",
+            Self::P14 => "p14",
+            Self::S1 => "s1",
+            Self::S2 => "s2",
+            Self::S3 => "s3",
+            Self::Spc1LikeRead => "spc1likeread",
+
+            Self::Loop => "loop",
+            Self::Multi1 => "multi1",
+            Self::Multi2 => "multi2",
+            Self::Multi3 => "multi3",
+            Self::TwoPools => "2-pools",
+            Self::Sprite => "sprite",
+            Self::ZigZag => "zigzag",
+
+            Self::Custom(custom) => return write!(f, "{}", custom.path.display()),
+            Self::Synthetic(workload) => return write!(f, "{workload}"),
+        };
+        write!(f, "{name}")
+    }
+}
+
 impl TraceFile {
+    pub fn custom(path: impl Into<PathBuf>, format: Option<TraceFileGroup>) -> Self {
+        Self::Custom(CustomTraceFile {
+            path: path.into(),
+            format,
+        })
+    }
+
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Self::Custom(_))
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        matches!(self, Self::Synthetic(_))
+    }
+
+    /// Returns the format of the lines in the trace file, or `None` if it should
+    /// be detected from each line.
+    pub fn format(&self) -> Option<TraceFileGroup> {
+        match self {
+            Self::Custom(custom) => custom.format,
+            Self::Synthetic(_) => None,
+            _ => Some(TraceFileGroup::new(self)),
+        }
+    }
+
+    /// Returns the path to the trace file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is a synthetic workload, which has no file.
     pub fn path(&self) -> PathBuf {
-        let mut p = Path::new(DATASET_DIR).to_path_buf();
-        p.push(TraceFileGroup::new(*self).sub_dir());
+        match self {
+            Self::Custom(custom) => return custom.path.clone(),
+            Self::Synthetic(workload) => {
+                panic!("A synthetic workload {workload} does not have a path")
+            }
+            _ => (),
+        }
+
+        let mut p = dataset_dir();
+        p.push(TraceFileGroup::new(self).sub_dir());
 
         match self {
             Self::ConCat => p.push("ConCat.lis"),
@@ -152,10 +304,54 @@ impl TraceFile {
             Self::TwoPools => p.push("2_pools.trc"),
             Self::Sprite => p.push("sprite.trc"),
             Self::ZigZag => p.push("zigzag.trc"),
+            Self::Custom(_) | Self::Synthetic(_) => unreachable!(),
         }
         p
     }
 
+    /// Opens the trace file for reading.
+    ///
+    /// If the expanded file (e.g. `S3.lis`) does not exist, this will look for the
+    /// Zstandard-compressed file (`S3.lis.zst`), and then for a multi-part archive
+    /// (`spc1likeread.lis.zst.*`) whose parts are concatenated in the name order
+    /// before decompressing.
+    pub(crate) fn open(&self) -> anyhow::Result<Box<dyn BufRead + Send>> {
+        let path = self.path();
+        if path.exists() {
+            let f = File::open(&path)
+                .with_context(|| format!("Cannot open trace file {}", path.display()))?;
+            if path.extension().is_some_and(|ext| ext == "zst") {
+                return decompress(f);
+            }
+            return Ok(Box::new(BufReader::new(f)));
+        }
+
+        let zst_path = append_extension(&path, "zst");
+        if zst_path.exists() {
+            let f = File::open(&zst_path)
+                .with_context(|| format!("Cannot open trace file {}", zst_path.display()))?;
+            return decompress(f);
+        }
+
+        let parts = find_parts(&zst_path)?;
+        if parts.is_empty() {
+            anyhow::bail!(
+                "Cannot find trace file {} (also tried {} and {}.*)",
+                path.display(),
+                zst_path.display(),
+                zst_path.display()
+            );
+        }
+
+        let mut reader: Box<dyn Read + Send> = Box::new(io::empty());
+        for part in parts {
+            let f = File::open(&part)
+                .with_context(|| format!("Cannot open trace file {}", part.display()))?;
+            reader = Box::new(reader.chain(f));
+        }
+        decompress(reader)
+    }
+
     pub fn default_capacities(&self) -> &[usize] {
         match self {
             Self::ConCat => &[200_000, 400_000, 3_200_000],
@@ -189,6 +385,87 @@ impl TraceFile {
             Self::TwoPools => &[128, 256, 512, 768],
             Self::Sprite => &[128, 256, 512, 768],
             Self::ZigZag => &[128, 256, 512, 768],
+
+            // There is no default for a custom trace file. Capacities must be
+            // given by `Config::capacities`.
+            Self::Custom(_) => &[],
+            Self::Synthetic(workload) => workload.default_capacities(),
         }
     }
 }
+
+fn decompress(reader: impl Read + Send + 'static) -> anyhow::Result<Box<dyn BufRead + Send>> {
+    let decoder = zstd::Decoder::new(reader)?;
+    Ok(Box::new(BufReader::new(decoder)))
+}
+
+pub(crate) fn append_extension(path: &Path, ext: &str) -> PathBuf {
+    let mut s = OsString::from(path.as_os_str());
+    s.push(".");
+    s.push(ext);
+    s.into()
+}
+
+/// Returns the parts of a multi-part archive (e.g. `spc1likeread.lis.zst.00`,
+/// `spc1likeread.lis.zst.01`, ...) sorted by their names. The suffixes must be the
+/// ones made by `split`, numeric or alphabetic, starting from `00` or `aa`.
+/// Returns an error if there is another file with the prefix, such as
+/// `spc1likeread.lis.zst.bak`, rather than decoding it as a part.
+pub(crate) fn find_parts(zst_path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let (Some(dir), Some(file_name)) = (zst_path.parent(), zst_path.file_name()) else {
+        return Ok(Vec::new());
+    };
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut prefix = file_name.to_os_string();
+    prefix.push(".");
+    let prefix = prefix.to_string_lossy().into_owned();
+
+    let mut parts = Vec::new();
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name().to_string_lossy().into_owned();
+        if let Some(suffix) = name.strip_prefix(&prefix) {
+            parts.push((suffix.to_string(), dir_entry.path()));
+        }
+    }
+    parts.sort();
+
+    let Some((first, _)) = parts.first() else {
+        return Ok(Vec::new());
+    };
+    let (width, is_numeric) = (first.len(), first.starts_with(|c: char| c.is_ascii_digit()));
+    for (i, (suffix, path)) in parts.iter().enumerate() {
+        if *suffix != split_suffix(i, width, is_numeric) {
+            anyhow::bail!(
+                "Unexpected file {} among the parts of {}. The parts must be named \
+                with the suffixes made by `split`, e.g. `.00`, `.01`, ... or `.aa`, \
+                `.ab`, ...",
+                path.display(),
+                zst_path.display()
+            );
+        }
+    }
+    Ok(parts.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Returns the `index`-th suffix of `width` characters made by `split`, e.g. `02`
+/// or `ac`.
+fn split_suffix(index: usize, width: usize, is_numeric: bool) -> String {
+    if is_numeric {
+        return format!("{index:0width$}");
+    }
+    let mut chars = Vec::with_capacity(width);
+    let mut n = index;
+    for _ in 0..width {
+        chars.push((b'a' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    if n > 0 {
+        // Out of the suffixes of the width.
+        return String::new();
+    }
+    chars.iter().rev().collect()
+}
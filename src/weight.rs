@@ -0,0 +1,199 @@
+//! The weights of the values in the size-aware mode, and the sizes in bytes given
+//! to the command line options.
+
+use std::{fmt, hash::BuildHasher};
+
+use crate::cache::DefaultHasher;
+
+/// The mean weight of the values when neither a distribution nor a weight column
+/// is given. The weights are hashed from the request ids into `4..2^16`.
+pub const DEFAULT_MEAN_WEIGHT: f64 = 32768.0;
+
+/// A distribution of the weights of the values, in bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeightDist {
+    /// All values have the same weight.
+    Fixed(u32),
+    /// The weights are uniformly distributed in `min..=max`.
+    Uniform { min: u32, max: u32 },
+    /// The logarithms of the weights are normally distributed. Most values are
+    /// small, with a long tail of large ones.
+    LogNormal { median: u32, sigma: f64 },
+    /// The values are either small or large, e.g. metadata and blobs.
+    Bimodal {
+        small: u32,
+        large: u32,
+        large_fraction: f64,
+    },
+}
+
+impl WeightDist {
+    /// Parses a distribution like `fixed:4KiB`, `uniform:512,64KiB`,
+    /// `lognormal:4KiB,1.5` or `bimodal:512,1MiB,0.05`.
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let (name, params) = spec.trim().split_once(':').unwrap_or((spec.trim(), ""));
+        let params = params.split(',').map(str::trim).collect::<Vec<_>>();
+        let size = |i: usize| -> anyhow::Result<u32> {
+            let v = params.get(i).copied().unwrap_or_default();
+            parse_byte_size(v)
+                .filter(|n| (1..=u32::MAX as u64).contains(n))
+                .map(|n| n as u32)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(r#"Cannot parse weight "{v}" of "{spec}" as a size in bytes"#)
+                })
+        };
+        let number = |i: usize| -> anyhow::Result<f64> {
+            let v = params.get(i).copied().unwrap_or_default();
+            v.parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n >= 0.0)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(r#"Cannot parse "{v}" of "{spec}" as a positive number"#)
+                })
+        };
+        let expect_params = |n: usize, usage: &str| {
+            if params.len() != n {
+                anyhow::bail!(r#"Expected "{usage}" in the weight distribution, but got "{spec}""#);
+            }
+            Ok(())
+        };
+
+        match name.to_lowercase().as_str() {
+            "fixed" => {
+                expect_params(1, "fixed:SIZE")?;
+                Ok(Self::Fixed(size(0)?))
+            }
+            "uniform" => {
+                expect_params(2, "uniform:MIN,MAX")?;
+                let (min, max) = (size(0)?, size(1)?);
+                if min > max {
+                    anyhow::bail!("The min weight {min} is greater than the max weight {max}");
+                }
+                Ok(Self::Uniform { min, max })
+            }
+            "lognormal" => {
+                expect_params(2, "lognormal:MEDIAN,SIGMA")?;
+                Ok(Self::LogNormal {
+                    median: size(0)?,
+                    sigma: number(1)?,
+                })
+            }
+            "bimodal" => {
+                expect_params(3, "bimodal:SMALL,LARGE,LARGE_FRACTION")?;
+                let large_fraction = number(2)?;
+                if large_fraction > 1.0 {
+                    anyhow::bail!(
+                        "The fraction of the large values must be at most 1, but got {large_fraction}"
+                    );
+                }
+                Ok(Self::Bimodal {
+                    small: size(0)?,
+                    large: size(1)?,
+                    large_fraction,
+                })
+            }
+            _ => anyhow::bail!(
+                r#"Unknown weight distribution "{name}". It must be "fixed", "uniform", "lognormal" or "bimodal""#
+            ),
+        }
+    }
+
+    /// Returns the weight of the request. It is drawn from the hash of the seed and
+    /// the request id, so that a request gets the same weight in every run and
+    /// regardless of which client sends it.
+    pub(crate) fn weight(&self, seed: u64, request_id: usize) -> u32 {
+        let hash = DefaultHasher.hash_one((seed, request_id));
+        // Two uniform numbers in (0, 1].
+        let u1 = ((hash >> 32) as f64 + 1.0) / 2f64.powi(32);
+        let u2 = ((hash as u32) as f64 + 1.0) / 2f64.powi(32);
+
+        match *self {
+            Self::Fixed(weight) => weight,
+            Self::Uniform { min, max } => {
+                let len = (max - min) as f64 + 1.0;
+                min + ((u1 * len) as u32).min(max - min)
+            }
+            Self::LogNormal { median, sigma } => {
+                // Box-Muller transform.
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                let weight = median as f64 * (sigma * z).exp();
+                weight.clamp(1.0, u32::MAX as f64) as u32
+            }
+            Self::Bimodal {
+                small,
+                large,
+                large_fraction,
+            } => {
+                if u1 <= large_fraction {
+                    large
+                } else {
+                    small
+                }
+            }
+        }
+    }
+
+    /// Returns the mean weight, used to convert the capacities in number of entries
+    /// into the max capacities in bytes.
+    pub fn mean(&self) -> f64 {
+        match *self {
+            Self::Fixed(weight) => weight as f64,
+            Self::Uniform { min, max } => (min as f64 + max as f64) / 2.0,
+            Self::LogNormal { median, sigma } => median as f64 * (sigma * sigma / 2.0).exp(),
+            Self::Bimodal {
+                small,
+                large,
+                large_fraction,
+            } => small as f64 * (1.0 - large_fraction) + large as f64 * large_fraction,
+        }
+    }
+}
+
+impl fmt::Display for WeightDist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(weight) => write!(f, "fixed({weight})"),
+            Self::Uniform { min, max } => write!(f, "uniform({min}..={max})"),
+            Self::LogNormal { median, sigma } => {
+                write!(f, "lognormal(median={median},sigma={sigma})")
+            }
+            Self::Bimodal {
+                small,
+                large,
+                large_fraction,
+            } => write!(f, "bimodal({small},{large},large={large_fraction})"),
+        }
+    }
+}
+
+/// Parses a size in bytes, e.g. `4096`, `64KiB`, `1.5GB`. The units are
+/// case-insensitive.
+pub fn parse_byte_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let unit_start = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(unit_start);
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000u64.pow(2),
+        "gb" => 1000u64.pow(3),
+        "tb" => 1000u64.pow(4),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return None,
+    };
+    if let Ok(n) = number.parse::<u64>() {
+        return n.checked_mul(multiplier);
+    }
+    let n = number.parse::<f64>().ok()? * multiplier as f64;
+    (n.is_finite() && n < u64::MAX as f64).then_some(n.round() as u64)
+}
+
+/// Returns `true` if the size has a unit, e.g. `64MiB` but not `65536`.
+pub fn has_byte_unit(s: &str) -> bool {
+    s.trim().ends_with(|c: char| c.is_ascii_alphabetic())
+}
@@ -0,0 +1,103 @@
+//! Checks that `moka-local/Cargo.toml` is the one generated from `Cargo.toml`, so
+//! that the two manifests never drift apart. After changing `Cargo.toml`, update
+//! it with:
+//!
+//! ```console
+//! $ UPDATE_MOKA_LOCAL_MANIFEST=1 cargo test --test moka_local_manifest
+//! ```
+
+use std::{fs, path::Path};
+
+use toml_edit::{value, Array, ArrayOfTables, DocumentMut, Item, Table};
+
+const UPDATE_ENV: &str = "UPDATE_MOKA_LOCAL_MANIFEST";
+
+const HEADER: &str = "\
+# Generated from `../Cargo.toml` by `tests/moka_local_manifest.rs`. Do not edit
+# this file. Run `UPDATE_MOKA_LOCAL_MANIFEST=1 cargo test --test moka_local_manifest`
+# after changing `../Cargo.toml`.
+#
+# Builds mokabench with the Moka checkout linked at `moka-local/checkout`, which
+# is ignored by git. See \"Benchmarking a Local Moka Checkout\" in the README.
+
+";
+
+/// Returns `moka-local/Cargo.toml` for the root manifest. It is the root manifest
+/// with the paths of the sources, and the `moka-local` feature and dependency.
+fn generate(root_manifest: &str) -> String {
+    let mut doc = root_manifest
+        .parse::<DocumentMut>()
+        .expect("Cargo.toml is not a valid TOML document");
+
+    let package = doc["package"].as_table_mut().expect("no [package] table");
+    package.insert("build", value("../build.rs"));
+    package.insert("publish", value(false));
+    let package_position = package.position();
+
+    // Place [lib] and [[bin]] right after [package].
+    let mut lib = Table::new();
+    lib.insert("path", value("../src/lib.rs"));
+    lib.set_position(package_position.unwrap_or_default());
+    doc.insert("lib", Item::Table(lib));
+
+    let mut bin = Table::new();
+    bin.insert("name", value("mokabench"));
+    bin.insert("path", value("../src/main.rs"));
+    bin.set_position(package_position.unwrap_or_default());
+    let mut bins = ArrayOfTables::new();
+    bins.push(bin);
+    doc.insert("bin", Item::ArrayOfTables(bins));
+
+    let features = doc["features"].as_table_mut().expect("no [features] table");
+    features["default"]
+        .as_array_mut()
+        .expect("no default features")
+        .push("moka-local");
+    let mut moka_local = Array::new();
+    moka_local.push("dep:mokalocal");
+    features.insert("moka-local", value(moka_local));
+    features
+        .key_mut("moka-local")
+        .unwrap()
+        .leaf_decor_mut()
+        .set_prefix("\n# The Moka checkout linked at `checkout`. It must have the v0.12 API.\n");
+
+    let mut moka_local_dep = Table::new();
+    moka_local_dep.insert("package", value("moka"));
+    moka_local_dep.insert("optional", value(true));
+    moka_local_dep.insert("path", value("checkout"));
+    let mut moka_features = Array::new();
+    moka_features.push("future");
+    moka_features.push("sync");
+    moka_local_dep.insert("features", value(moka_features));
+    doc["dependencies"]
+        .as_table_mut()
+        .expect("no [dependencies] table")
+        .insert("mokalocal", Item::Table(moka_local_dep));
+
+    // The root manifest only declares the `moka-local` feature for check-cfg.
+    doc.remove("lints");
+
+    format!("{HEADER}{doc}")
+}
+
+#[test]
+fn moka_local_manifest_is_up_to_date() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let root_manifest = fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+    let path = dir.join("moka-local").join("Cargo.toml");
+    let expected = generate(&root_manifest);
+
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        fs::write(&path, expected).unwrap();
+        return;
+    }
+
+    let actual = fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        actual == expected,
+        "{} is not up to date with Cargo.toml. Run `{UPDATE_ENV}=1 cargo test --test \
+        moka_local_manifest` to update it",
+        path.display()
+    );
+}